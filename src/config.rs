@@ -0,0 +1,128 @@
+use core::fmt::{self, Display};
+
+use crate::{
+    env,
+    io::{self, FdReader},
+    parse::{self, LineError, Options},
+};
+
+/// Largest config file read.
+pub const CONFIG_MAX: usize = 4096;
+pub const PATH_MAX: usize = nc::PATH_MAX as _;
+const FILE_NAME: &[u8] = b"config";
+
+/// `struct inotify_event` up to its name, which is `len` bytes of NULs after it.
+const EVENT_SIZE: usize = 16;
+
+pub enum Error {
+    Read(nc::Errno),
+    Line(LineError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Read(e) => write!(f, "cannot read it (error {e})"),
+            Error::Line(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/clock/config`, or `$HOME/.config/clock/config` when the
+/// former is unset or not absolute. `None` without a home either.
+pub fn path(buf: &mut [u8; PATH_MAX]) -> Option<&[u8]> {
+    let (dir, file): (_, &[u8]) = match env::var(b"XDG_CONFIG_HOME") {
+        Some(dir) if dir.first() == Some(&b'/') => (dir, b"/clock/config"),
+        _ => (
+            env::var(b"HOME").filter(|home| !home.is_empty())?,
+            b"/.config/clock/config",
+        ),
+    };
+    let len = dir.len() + file.len();
+    if len > buf.len() {
+        return None;
+    }
+    buf[..dir.len()].copy_from_slice(dir);
+    buf[dir.len()..len].copy_from_slice(file);
+    Some(&buf[..len])
+}
+
+/// Parses the config file at `path`, read into `buf`. No file sets nothing.
+pub fn read(path: &[u8], buf: &'static mut [u8]) -> Result<Options, Error> {
+    let mut options = Options::default();
+    match load(path, buf) {
+        Ok(text) => parse::config(text, &mut options).map_err(Error::Line)?,
+        Err(nc::ENOENT) => {}
+        Err(e) => return Err(Error::Read(e)),
+    }
+    Ok(options)
+}
+
+/// Reads the config file at `path` into `buf`.
+pub fn load(path: &[u8], buf: &'static mut [u8]) -> io::Result<&'static [u8]> {
+    let file = FdReader::open(path)?;
+    let len = file.read_to_end(buf);
+    _ = file.close();
+    Ok(&buf[..len?])
+}
+
+/// An inotify fd watching the directory of the config file at `path`, so
+/// that it is seen however an editor saves it, even if it is created later.
+pub fn watch(path: &[u8]) -> io::Result<i32> {
+    let dir = &path[..path.len() - FILE_NAME.len()];
+    let mut buf = [0; PATH_MAX];
+    buf[..dir.len()].copy_from_slice(dir);
+    let fd = unsafe { nc::inotify_init1(nc::IN_CLOEXEC)? };
+    let mask = nc::IN_CLOSE_WRITE | nc::IN_MOVE | nc::IN_DELETE | nc::IN_ONLYDIR;
+    match unsafe {
+        nc::syscalls::syscall3(
+            nc::SYS_INOTIFY_ADD_WATCH,
+            fd as _,
+            buf.as_ptr() as _,
+            mask as _,
+        )
+    } {
+        Ok(_) => Ok(fd),
+        Err(e) => {
+            _ = unsafe { nc::close(fd) };
+            Err(e)
+        }
+    }
+}
+
+/// Whether inotify `events` are about the config file, or may have been.
+pub fn changed(events: &[u8]) -> bool {
+    let mut rest = events;
+    while let Some(event) = rest.get(..EVENT_SIZE) {
+        let field =
+            |i: usize| u32::from_ne_bytes([event[i], event[i + 1], event[i + 2], event[i + 3]]);
+        let len = field(12) as usize;
+        let name = rest.get(EVENT_SIZE..EVENT_SIZE + len).unwrap_or_default();
+        if field(4) & nc::IN_Q_OVERFLOW != 0 || name.split(|&b| b == 0).next() == Some(FILE_NAME) {
+            return true;
+        }
+        rest = rest.get(EVENT_SIZE + len..).unwrap_or_default();
+    }
+    false
+}
+
+#[test]
+fn test_changed() {
+    let event = |mask: u32, name: &[u8]| {
+        let mut event = [0; EVENT_SIZE + 16];
+        event[4..8].copy_from_slice(&mask.to_ne_bytes());
+        event[12..16].copy_from_slice(&16u32.to_ne_bytes());
+        event[EVENT_SIZE..][..name.len()].copy_from_slice(name);
+        event
+    };
+    let swap = event(nc::IN_CLOSE_WRITE, b".config.swp");
+    let config = event(nc::IN_MOVED_TO, b"config");
+    assert!(!changed(&swap));
+    let mut both = [0; 2 * (EVENT_SIZE + 16)];
+    both[..swap.len()].copy_from_slice(&swap);
+    both[swap.len()..].copy_from_slice(&config);
+    assert!(changed(&both));
+    assert!(changed(&event(nc::IN_Q_OVERFLOW, b"")));
+    assert!(!changed(&event(nc::IN_DELETE, b"configs")));
+    assert!(!changed(&[]));
+}
@@ -1,13 +1,114 @@
+use core::ops::Deref;
+
 use crate::io::{self, Write};
+use color::{Color, Literal};
+use frame::{Cell, FULL_BLOCK, Frame};
 
 pub mod color;
+pub mod frame;
 
 pub const COLOR_SEQUENCE_SISE: usize = 19;
 
-pub struct Context<Writer: Write> {
+/// Blank columns between blocks drawn side by side.
+pub const GAP_WIDTH: usize = 5;
+
+/// A blank as wide as a digit, standing for a suppressed leading zero.
+pub const BLANK: u8 = b'_';
+
+/// Unchanged cells rewritten rather than moving the cursor over them.
+const MAX_SKIP: usize = 8;
+
+/// Fonts from the largest to the smallest.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Font {
+    #[default]
+    Large,
+    Compact,
+    /// Plain characters on a single line.
+    Text,
+}
+
+/// Draws into the next frame, then writes only what changed since the frame
+/// before it.
+pub struct Context<'a, Writer: Write> {
     pub writer: Writer,
+    pub palette: Palette,
+    /// Colors last written, `None` when unknown.
+    style: Option<Style>,
+    pub font: Font,
+    /// Cells per font cell, in both directions.
+    pub scale: usize,
+    frames: &'a mut [Frame; 2],
+    /// Index of the frame being drawn; the other one is on screen.
+    next: usize,
+    /// Palette of the frame on screen, `None` when nothing is.
+    shown_palette: Option<Palette>,
+    /// Whether frames are written as synchronized updates.
+    pub synchronized: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+}
+
+/// What a cell belongs to, giving its colors.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Element {
+    Screen,
+    Digits,
+    Colon,
+    Labels,
+}
+
+/// Colors of each element, set with `--fg` and `--bg`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// Background of the whole screen.
+    pub background: Color,
+    pub digits: Style,
+    /// Colons and the stopwatch's dot.
+    pub colon: Style,
+    /// Dates, zone labels and stopwatch laps.
+    pub labels: Style,
+}
+
+impl Palette {
+    pub const fn uniform(fg: Color, bg: Color) -> Self {
+        let style = Style { fg, bg };
+        Self {
+            background: bg,
+            digits: style,
+            colon: style,
+            labels: style,
+        }
+    }
+
+    pub const fn screen(&self) -> Style {
+        Style {
+            fg: Color::Clear,
+            bg: self.background,
+        }
+    }
+
+    pub const fn style(&self, element: Element) -> Style {
+        match element {
+            Element::Screen => self.screen(),
+            Element::Digits => self.digits,
+            Element::Colon => self.colon,
+            Element::Labels => self.labels,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::uniform(Color::Bright(Literal::Blue), Color::Clear)
+    }
 }
 
+/// A run of `n` filled (`on`) or blank (`off`) cells.
 #[derive(Clone, Copy)]
 pub struct Draw(i8);
 
@@ -23,98 +124,414 @@ impl Draw {
         Self::new(-(data as i8))
     }
 
-    const NOP: Self = Self::new(0);
+    const LONG: Row = &[Self::on(5)];
+    const LEFT: Row = &[Self::on(2), Self::off(3)];
+    const RIGHT: Row = &[Self::off(3), Self::on(2)];
+    const LEFT_AND_RIGHT: Row = &[Self::on(2), Self::off(1), Self::on(2)];
+    const ONE: Row = &[Self::off(1), Self::on(2)];
 
-    const LONG: [Self; 3] = [Self::on(5), Self::NOP, Self::NOP];
-    const LEFT: [Self; 3] = [Self::on(2), Self::off(3), Self::NOP];
-    const RIGHT: [Self; 3] = [Self::off(3), Self::on(2), Self::NOP];
-    const LEFT_AND_RIGHT: [Self; 3] = [Self::on(2), Self::off(1), Self::on(2)];
-    const ONE: [Self; 3] = [Self::off(1), Self::on(2), Self::NOP];
+    const SHORT: Row = &[Self::on(3)];
+    const SHORT_LEFT: Row = &[Self::on(1), Self::off(2)];
+    const SHORT_RIGHT: Row = &[Self::off(2), Self::on(1)];
+    const SHORT_LEFT_AND_RIGHT: Row = &[Self::on(1), Self::off(1), Self::on(1)];
 }
 
-fn space(n: usize) -> &'static [u8] {
-    const SPACES: [u8; 3] = [b' '; 3];
-    unsafe { &SPACES.get_unchecked(..n) }
+/// Columns of the widest row of `glyph`.
+fn glyph_width(glyph: &DrawLineN) -> usize {
+    let row_width = |row: &Row| {
+        row.iter()
+            .map(|d| d.0.unsigned_abs() as usize)
+            .sum::<usize>()
+    };
+    glyph.iter().map(row_width).max().unwrap_or(0)
 }
 
-fn block(n: usize) -> &'static [u8] {
-    const BLOCKS: &[u8] = "█████".as_bytes();
-    unsafe { &BLOCKS.get_unchecked(..n * 3) }
+impl Font {
+    /// `None` for `Text`.
+    fn glyph(self, c: u8) -> Option<&'static DrawLineN> {
+        let (digits, blank, [a, p, m]) = match self {
+            Font::Large => (&DIGITS, &BLANK_DIGIT, &LETTERS),
+            Font::Compact => (&COMPACT_DIGITS, &COMPACT_BLANK_DIGIT, &COMPACT_LETTERS),
+            Font::Text => return None,
+        };
+        Some(match c {
+            b'0'..=b'9' => &digits[(c - b'0') as usize],
+            BLANK => blank,
+            b'A' => a,
+            b'P' => p,
+            b'M' => m,
+            b':' => &COLON,
+            b'.' => &DOT,
+            _ => &SPACE,
+        })
+    }
+
+    /// Lines of each glyph, before scaling.
+    pub fn height(self) -> usize {
+        self.glyph(b'0').map_or(1, <[Row]>::len)
+    }
+
+    /// Columns taken by `text` with all its digits as wide as the widest,
+    /// before scaling.
+    pub fn width(self, text: &[u8]) -> usize {
+        match self {
+            Font::Text => text.len(),
+            _ => text
+                .iter()
+                .filter_map(|&c| match c {
+                    b'0'..=b'9' | BLANK => self.glyph(b'0'),
+                    c => self.glyph(c),
+                })
+                .map(|glyph| glyph_width(glyph) + 1)
+                .sum::<usize>()
+                .saturating_sub(1),
+        }
+    }
 }
 
-impl<Writer: Write> Context<Writer> {
-    pub const fn new(writer: Writer) -> Self {
-        Self { writer }
+impl<'a, Writer: Write> Context<'a, Writer> {
+    pub fn new(writer: Writer, frames: &'a mut [Frame; 2]) -> Self {
+        frames.iter_mut().for_each(|frame| frame.clear(0, 0));
+        Self {
+            writer,
+            palette: Palette::default(),
+            style: None,
+            font: Font::Large,
+            scale: 1,
+            frames,
+            next: 0,
+            shown_palette: None,
+            synchronized: false,
+        }
     }
 
-    fn space(&mut self, n: usize) -> io::Result<()> {
-        self.writer.write_all(space(n))
+    fn frame(&mut self) -> &mut Frame {
+        &mut self.frames[self.next]
     }
 
-    fn block(&mut self, n: usize) -> io::Result<()> {
-        self.writer.write_all(block(n))
+    pub fn fg(&mut self, color: Color) -> io::Result<()> {
+        let mut buf = [0; COLOR_SEQUENCE_SISE];
+        let len = color.ansi_sequence_fg(&mut buf);
+        self.writer.write_all(&buf[..len])
     }
 
-    fn do_draw(&mut self, Draw(data): Draw) -> io::Result<()> {
-        match data.signum() {
-            1 => self.block(data as _),
-            -1 => self.space(-data as _),
-            _ => Ok(()),
+    pub fn bg(&mut self, color: Color) -> io::Result<()> {
+        let mut buf = [0; COLOR_SEQUENCE_SISE];
+        let len = color.ansi_sequence_bg(&mut buf);
+        self.writer.write_all(&buf[..len])
+    }
+
+    /// Switches colors, writing only what changed since the last call.
+    pub fn style(&mut self, style: Style) -> io::Result<()> {
+        let last = self.style.replace(style);
+        if last.map(|last| last.fg) != Some(style.fg) {
+            self.fg(style.fg)?;
+        }
+        if last.map(|last| last.bg) != Some(style.bg) {
+            self.bg(style.bg)?;
         }
+        Ok(())
     }
 
-    pub fn draw<R: IntoIterator<Item = &'static DrawLineN>>(
+    /// Forgets what the screen shows, so the next frame is written whole.
+    pub fn invalidate(&mut self) {
+        self.style = None;
+        self.shown_palette = None;
+    }
+
+    fn element(c: u8) -> Element {
+        match c {
+            b':' | b'.' => Element::Colon,
+            _ => Element::Digits,
+        }
+    }
+
+    /// Starts a blank frame of `columns` by `lines`.
+    pub fn clear(&mut self, columns: usize, lines: usize) {
+        self.frame().clear(columns, lines);
+    }
+
+    pub fn text(&mut self, line: usize, column: usize, text: &[u8], element: Element) {
+        self.frame().text(line, column, text, element);
+    }
+
+    /// Draws each text as a block in `font`, `scale` times larger and
+    /// `GAP_WIDTH` apart, then lines of captions, each centered under its block.
+    /// Blocks are `Font::width` wide and narrower digits are padded on the
+    /// left to the widest, so nothing moves as digits change.
+    /// Returns the line after the last one drawn.
+    pub fn draw(
         &mut self,
-        margin_left: Option<&[u8]>,
-        string: impl Fn() -> R,
-    ) -> io::Result<()> {
-        for line in 0..LINE_COUNT {
-            if let Some(x) = margin_left {
-                self.writer.write_all(x)?;
+        top: usize,
+        left: usize,
+        texts: &[&[u8]],
+        captions: &[&[&[u8]]],
+    ) -> usize {
+        let block_width = texts
+            .iter()
+            .map(|text| self.font.width(text))
+            .max()
+            .unwrap_or(0);
+        let (font, scale) = (self.font, self.scale);
+        let (block_width, gap_width) = (block_width * scale, GAP_WIDTH * scale);
+        let digit_width = font.glyph(b'0').map_or(0, glyph_width);
+        let frame = &mut self.frames[self.next];
+        let mut line = top;
+        // a glyph row is `scale` lines alike
+        for row in 0..font.height() {
+            for (i, text) in texts.iter().enumerate() {
+                let mut column = left + i * (block_width + gap_width);
+                for (j, &c) in text.iter().enumerate() {
+                    let element = Self::element(c);
+                    let Some(glyph) = font.glyph(c) else {
+                        let c = if c == BLANK { b' ' } else { c };
+                        frame.fill_lines(line, scale, column, 1, Cell { c, element });
+                        column += 1;
+                        continue;
+                    };
+                    let blank = Cell { c: b' ', element };
+                    let mut pad = match c {
+                        b'0'..=b'9' => digit_width.saturating_sub(glyph_width(glyph)),
+                        _ => 0,
+                    } * scale;
+                    if j > 0 {
+                        pad += scale;
+                    }
+                    frame.fill_lines(line, scale, column, pad, blank);
+                    column += pad;
+                    for &Draw(data) in glyph[row] {
+                        let n = data.unsigned_abs() as usize * scale;
+                        let c = if data > 0 { FULL_BLOCK } else { b' ' };
+                        frame.fill_lines(line, scale, column, n, Cell { c, element });
+                        column += n;
+                    }
+                }
             }
-            let string = string();
-            for &draw_line_n in string {
-                let draw_list = draw_line_n[line];
-                for draw in draw_list {
-                    self.do_draw(draw)?;
+            line += scale;
+        }
+        for &captions in captions.iter().filter(|line| !line.is_empty()) {
+            for (i, caption) in captions.iter().enumerate() {
+                let caption = &caption[..caption.len().min(block_width)];
+                let pad = (block_width - caption.len()) / 2;
+                let column = left + i * (block_width + gap_width) + pad;
+                frame.text(line, column, caption, Element::Labels);
+            }
+            line += 1;
+        }
+        line
+    }
+
+    fn write_cell(&mut self, Cell { c, element }: Cell) -> io::Result<()> {
+        self.style(self.palette.style(element))?;
+        match c {
+            FULL_BLOCK => self.writer.write_all("█".as_bytes()),
+            c => self.writer.write_all(&[c]),
+        }
+    }
+
+    fn cursor_to(&mut self, line: usize, column: usize) -> io::Result<()> {
+        self.writer.write_all(b"[")?;
+        self.writer.write_u64(line as u64 + 1)?;
+        self.writer.write_all(b";")?;
+        self.writer.write_u64(column as u64 + 1)?;
+        self.writer.write_all(b"H")
+    }
+
+    /// Writes the cells that differ from the frame on screen, all of them
+    /// after a resize or a change of palette, then shows the new frame.
+    pub fn present(&mut self) -> io::Result<()> {
+        if self.synchronized {
+            self.writer.write_all(crate::begin_synchronized!())?;
+        }
+        let (columns, lines) = (self.frame().columns, self.frame().lines);
+        let shown = 1 - self.next;
+        let size = (self.frames[shown].columns, self.frames[shown].lines);
+        if size != (columns, lines) || self.shown_palette != Some(self.palette) {
+            self.frames[shown].clear(columns, lines);
+            self.style = None;
+            self.style(self.palette.screen())?;
+            self.writer.write_all(concat_bytes!(
+                crate::cursor_position!(),
+                crate::clear_screen!()
+            ))?;
+            self.shown_palette = Some(self.palette);
+        }
+
+        // where the cursor is, `None` when unknown
+        let mut cursor = None;
+        let mut new_cells = [Cell::BLANK; frame::MAX_COLUMNS];
+        let mut old_cells = [Cell::BLANK; frame::MAX_COLUMNS];
+        for line in 0..lines {
+            let (new, old) = (
+                self.frames[self.next].line(line, &mut new_cells),
+                self.frames[shown].line(line, &mut old_cells),
+            );
+            if new == old {
+                continue;
+            }
+            // multibyte characters take fewer columns than cells, so their
+            // lines are written whole
+            if new.iter().chain(old).any(|cell| !cell.c.is_ascii()) {
+                let end = new.iter().rposition(|&cell| cell != Cell::BLANK);
+                self.cursor_to(line, 0)?;
+                for &cell in &new[..end.map_or(0, |end| end + 1)] {
+                    self.write_cell(cell)?;
                 }
-                self.do_draw(Draw::off(1))?;
+                self.style(self.palette.screen())?;
+                self.writer.write_all(b"[K")?;
+                cursor = None;
+                continue;
+            }
+            for column in 0..columns {
+                let cell = new[column];
+                if cell == old[column] {
+                    continue;
+                }
+                match cursor {
+                    Some((l, c)) if l == line && c <= column && column - c <= MAX_SKIP => {
+                        for &cell in &new[c..column] {
+                            self.write_cell(cell)?;
+                        }
+                    }
+                    _ => self.cursor_to(line, column)?,
+                }
+                self.write_cell(cell)?;
+                cursor = Some((line, column + 1));
             }
-            self.writer.write_all(b"\n")?;
+        }
+        self.next = shown;
+        if self.synchronized {
+            self.writer.write_all(crate::end_synchronized!())?;
         }
         Ok(())
     }
 }
 
-pub fn draw_time(seconds: isize) -> [&'static DrawLineN; 8] {
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Hours {
+    #[default]
+    H24,
+    /// 1 to 12, followed by `AM` or `PM`.
+    H12,
+}
+
+/// Characters to draw, such as `12:34 PM`.
+#[derive(Clone, Copy)]
+pub struct Text {
+    buf: [u8; 12],
+    len: usize,
+}
+
+impl Text {
+    /// Joins `parts`, cutting what doesn't fit.
+    pub fn new(parts: &[&[u8]]) -> Self {
+        let mut text = Self {
+            buf: [0; 12],
+            len: 0,
+        };
+        for part in parts {
+            let len = part.len().min(text.buf.len() - text.len);
+            text.buf[text.len..text.len + len].copy_from_slice(&part[..len]);
+            text.len += len;
+        }
+        text
+    }
+}
+
+impl Deref for Text {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+/// `HH:MM:SS`, and ` AM` or ` PM` to follow it in 12-hour mode. Without
+/// `leading_zero`, a zero first digit is `BLANK`.
+pub fn format_time(seconds: i64, hours: Hours, leading_zero: bool) -> ([u8; 8], &'static [u8]) {
     let [s, min, h] = time(seconds);
-    let arr = unsafe {
-        [
-            DIGITS.get_unchecked((h / 10) as usize),
-            DIGITS.get_unchecked((h % 10) as usize),
-            &COLON,
-            DIGITS.get_unchecked((min / 10) as usize),
-            DIGITS.get_unchecked((min % 10) as usize),
-            &COLON,
-            DIGITS.get_unchecked((s / 10) as usize),
-            DIGITS.get_unchecked((s % 10) as usize),
-        ]
+    let (h, meridiem): (_, &[u8]) = match hours {
+        Hours::H24 => (h, b""),
+        Hours::H12 => ((h + 11) % 12 + 1, if h < 12 { b" AM" } else { b" PM" }),
     };
-    arr
+    let digit = |n: i64| b'0' + (n % 10) as u8;
+    let first = match h / 10 {
+        0 if !leading_zero => BLANK,
+        tens => digit(tens),
+    };
+    let time = [
+        first,
+        digit(h),
+        b':',
+        digit(min / 10),
+        digit(min),
+        b':',
+        digit(s / 10),
+        digit(s),
+    ];
+    (time, meridiem)
+}
+
+/// `MM:SS.cc` below an hour, `HH:MM:SS` from then on.
+pub fn format_stopwatch(centis: u64) -> [u8; 8] {
+    let seconds = centis / 100;
+    let digit = |n: u64| b'0' + (n % 10) as u8;
+    let (min, s) = (seconds / 60 % 60, seconds % 60);
+    match seconds / 3600 {
+        0 => [
+            digit(min / 10),
+            digit(min),
+            b':',
+            digit(s / 10),
+            digit(s),
+            b'.',
+            digit(centis / 10),
+            digit(centis),
+        ],
+        h => [
+            digit(h / 10),
+            digit(h),
+            b':',
+            digit(min / 10),
+            digit(min),
+            b':',
+            digit(s / 10),
+            digit(s),
+        ],
+    }
+}
+
+/// `HH:MM:SS` of a timer, whose hours go past a day.
+pub fn format_timer(seconds: u64) -> [u8; 8] {
+    let digit = |n: u64| b'0' + (n % 10) as u8;
+    let (h, min, s) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    [
+        digit(h / 10),
+        digit(h),
+        b':',
+        digit(min / 10),
+        digit(min),
+        b':',
+        digit(s / 10),
+        digit(s),
+    ]
 }
 
 #[must_use]
-pub fn time(seconds: isize) -> [isize; 3] {
+pub fn time(seconds: i64) -> [i64; 3] {
     let s = seconds % 60;
     let min = (seconds / 60) % 60;
     let h = (seconds / 3600) % 24;
     [s, min, h]
 }
 
+/// Lines of the large and compact fonts, before scaling.
 const LINE_COUNT: usize = 5;
-type DrawLineN = [[Draw; 3]; LINE_COUNT];
+type Row = &'static [Draw];
+/// A glyph, one row of runs per line.
+type DrawLineN = [Row];
 
-const DIGITS: [DrawLineN; 10] = [
+const DIGITS: [[Row; LINE_COUNT]; 10] = [
     [
         Draw::LONG,
         Draw::LEFT_AND_RIGHT,
@@ -163,10 +580,256 @@ const DIGITS: [DrawLineN; 10] = [
     ],
 ];
 
-const COLON: DrawLineN = [
-    [Draw::off(1), Draw::NOP, Draw::NOP],
-    [Draw::on(1), Draw::NOP, Draw::NOP],
-    [Draw::off(1), Draw::NOP, Draw::NOP],
-    [Draw::on(1), Draw::NOP, Draw::NOP],
-    [Draw::off(1), Draw::NOP, Draw::NOP],
+const COMPACT_DIGITS: [[Row; LINE_COUNT]; 10] = [
+    [
+        Draw::SHORT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT,
+    ],
+    [Draw::SHORT_RIGHT; LINE_COUNT],
+    [
+        Draw::SHORT,
+        Draw::SHORT_RIGHT,
+        Draw::SHORT,
+        Draw::SHORT_LEFT,
+        Draw::SHORT,
+    ],
+    [
+        Draw::SHORT,
+        Draw::SHORT_RIGHT,
+        Draw::SHORT,
+        Draw::SHORT_RIGHT,
+        Draw::SHORT,
+    ],
+    [
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT,
+        Draw::SHORT_RIGHT,
+        Draw::SHORT_RIGHT,
+    ],
+    [
+        Draw::SHORT,
+        Draw::SHORT_LEFT,
+        Draw::SHORT,
+        Draw::SHORT_RIGHT,
+        Draw::SHORT,
+    ],
+    [
+        Draw::SHORT,
+        Draw::SHORT_LEFT,
+        Draw::SHORT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT,
+    ],
+    [
+        Draw::SHORT,
+        Draw::SHORT_RIGHT,
+        Draw::SHORT_RIGHT,
+        Draw::SHORT_RIGHT,
+        Draw::SHORT_RIGHT,
+    ],
+    [
+        Draw::SHORT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT,
+    ],
+    [
+        Draw::SHORT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT,
+        Draw::SHORT_RIGHT,
+        Draw::SHORT,
+    ],
+];
+
+const COLON: [Row; LINE_COUNT] = [
+    &[Draw::off(1)],
+    &[Draw::on(1)],
+    &[Draw::off(1)],
+    &[Draw::on(1)],
+    &[Draw::off(1)],
 ];
+
+const SPACE: [Row; LINE_COUNT] = [&[Draw::off(1)]; LINE_COUNT];
+const BLANK_DIGIT: [Row; LINE_COUNT] = [&[Draw::off(5)]; LINE_COUNT];
+const COMPACT_BLANK_DIGIT: [Row; LINE_COUNT] = [&[Draw::off(3)]; LINE_COUNT];
+
+/// `A`, `P` and `M`.
+const LETTERS: [[Row; LINE_COUNT]; 3] = [
+    [
+        Draw::LONG,
+        Draw::LEFT_AND_RIGHT,
+        Draw::LONG,
+        Draw::LEFT_AND_RIGHT,
+        Draw::LEFT_AND_RIGHT,
+    ],
+    [
+        Draw::LONG,
+        Draw::LEFT_AND_RIGHT,
+        Draw::LONG,
+        Draw::LEFT,
+        Draw::LEFT,
+    ],
+    [
+        &[Draw::on(1), Draw::off(3), Draw::on(1)],
+        &[Draw::on(2), Draw::off(1), Draw::on(2)],
+        &[
+            Draw::on(1),
+            Draw::off(1),
+            Draw::on(1),
+            Draw::off(1),
+            Draw::on(1),
+        ],
+        &[Draw::on(1), Draw::off(3), Draw::on(1)],
+        &[Draw::on(1), Draw::off(3), Draw::on(1)],
+    ],
+];
+
+const COMPACT_LETTERS: [[Row; LINE_COUNT]; 3] = [
+    [
+        Draw::SHORT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+    ],
+    [
+        Draw::SHORT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT,
+        Draw::SHORT_LEFT,
+        Draw::SHORT_LEFT,
+    ],
+    [
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+        Draw::SHORT_LEFT_AND_RIGHT,
+    ],
+];
+
+const DOT: [Row; LINE_COUNT] = [
+    &[Draw::off(1)],
+    &[Draw::off(1)],
+    &[Draw::off(1)],
+    &[Draw::off(1)],
+    &[Draw::on(1)],
+];
+
+#[test]
+fn test_format_time() {
+    // 13:05:09
+    let t = 13 * 3600 + 5 * 60 + 9;
+    assert_eq!(format_time(t, Hours::H24, true), (*b"13:05:09", &b""[..]));
+    assert_eq!(
+        format_time(t, Hours::H12, true),
+        (*b"01:05:09", &b" PM"[..])
+    );
+    assert_eq!(
+        format_time(t, Hours::H12, false),
+        (*b"_1:05:09", &b" PM"[..])
+    );
+    assert_eq!(
+        format_time(5, Hours::H12, true),
+        (*b"12:00:05", &b" AM"[..])
+    );
+    assert_eq!(format_time(12 * 3600, Hours::H12, false).1, b" PM");
+    let text = Text::new(&[&b"01:05:09"[..5], b" PM"]);
+    assert_eq!(&*text, b"01:05 PM");
+    assert_eq!(Font::Large.width(&text), Font::Large.width(b"00:00 AM"));
+    assert_eq!(Font::Large.width(b"_1:05"), Font::Large.width(b"00:00"));
+}
+
+#[test]
+fn test_format_timer() {
+    assert_eq!(&format_timer(25 * 60), b"00:25:00");
+    assert_eq!(&format_timer(30 * 3600 + 61), b"30:01:01");
+    assert_eq!(&format_timer(100 * 3600 - 1), b"99:59:59");
+}
+
+#[test]
+fn test_present() {
+    static mut FRAMES: core::mem::MaybeUninit<[Frame; 2]> = core::mem::MaybeUninit::uninit();
+    let mut buf = [0; 4096];
+    #[allow(static_mut_refs)]
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf), unsafe {
+        FRAMES.assume_init_mut()
+    });
+    let mut present = |text: &[u8]| {
+        ctx.writer.len = 0;
+        ctx.clear(20, 3);
+        ctx.text(1, 2, text, Element::Labels);
+        ctx.present().unwrap();
+        ctx.writer.len
+    };
+    // the first frame is written whole
+    assert!(present(b"12:00") > 0);
+    assert_eq!(present(b"12:00"), 0);
+    // a move to the changed cell and the cell itself
+    assert_eq!(present(b"12:01"), b"\x1b[2;7H1".len());
+}
+
+#[test]
+fn test_most_zones() {
+    static mut FRAMES: core::mem::MaybeUninit<[Frame; 2]> = core::mem::MaybeUninit::uninit();
+    let mut buf = [0; 4096];
+    #[allow(static_mut_refs)]
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf), unsafe {
+        FRAMES.assume_init_mut()
+    });
+    // every zone at its longest, in as many runs as a frame takes
+    let texts = [&b"10:08:48 AM"[..]; crate::parse::MAX_ZONES];
+    let text: [u8; 128] = core::array::from_fn(|i| b'a' + (i % 26) as u8);
+    let dates = [&text[..64]; crate::parse::MAX_ZONES];
+    let labels = [&b"America/Argentina/Buenos_Aires"[..]; crate::parse::MAX_ZONES];
+    ctx.clear(frame::MAX_COLUMNS, frame::MAX_LINES);
+    ctx.text(frame::MAX_LINES - 1, 0, &text, Element::Labels);
+    let line = ctx.draw(0, 0, &texts, &[&dates, &labels]);
+    let mut cells = [Cell::BLANK; frame::MAX_COLUMNS];
+    let labels = ctx.frames[ctx.next].line(line - 1, &mut cells);
+    let underscores = labels.iter().filter(|cell| cell.c == b'_').count();
+    assert_eq!(underscores, crate::parse::MAX_ZONES);
+}
+
+#[test]
+fn test_glyph_heights() {
+    assert_eq!(Font::Text.height(), 1);
+    for font in [Font::Large, Font::Compact] {
+        for &c in b"0123456789_AMP:. " {
+            assert_eq!(font.glyph(c).unwrap().len(), font.height());
+        }
+    }
+}
+
+#[test]
+fn test_digits_stay_put() {
+    static mut FRAMES: core::mem::MaybeUninit<[Frame; 2]> = core::mem::MaybeUninit::uninit();
+    let mut buf = [0; 4096];
+    #[allow(static_mut_refs)]
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf), unsafe {
+        FRAMES.assume_init_mut()
+    });
+    ctx.scale = 2;
+    ctx.clear(40, 20);
+    let height = ctx.font.height() * ctx.scale;
+    ctx.draw(0, 0, &[b"10"], &[]);
+    ctx.draw(height, 0, &[b"00"], &[]);
+    let frame = &ctx.frames[ctx.next];
+    for line in 0..height {
+        let (mut ones, mut zeros) = (
+            [Cell::BLANK; frame::MAX_COLUMNS],
+            [Cell::BLANK; frame::MAX_COLUMNS],
+        );
+        let ones = frame.line(line, &mut ones);
+        let zeros = frame.line(height + line, &mut zeros);
+        // the `0`s line up, and the `1` keeps to the right of its cell
+        assert!(ones[12..] == zeros[12..]);
+        assert!(ones[..4].iter().all(|cell| cell.c == b' '));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{draw::COLOR_SEQUENCE_SISE, io};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Literal {
     Black,
     Red,
@@ -12,7 +12,7 @@ pub enum Literal {
     White,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Color {
     Normal(Literal),
     Bright(Literal),
@@ -21,7 +21,54 @@ pub enum Color {
     Clear,
 }
 
+impl Literal {
+    const ALL: [(Self, &[u8]); 8] = [
+        (Self::Black, b"black"),
+        (Self::Red, b"red"),
+        (Self::Green, b"green"),
+        (Self::Yellow, b"yellow"),
+        (Self::Blue, b"blue"),
+        (Self::Magenta, b"magenta"),
+        (Self::Cyan, b"cyan"),
+        (Self::White, b"white"),
+    ];
+
+    fn parse(name: &[u8]) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|&(literal, _)| literal)
+    }
+}
+
 impl Color {
+    /// Parses `NAME`, `bright-NAME`, `default`, `ansi:N` or `#rrggbb`.
+    pub fn parse(s: &[u8]) -> Option<Self> {
+        let hex = |s: &[u8]| {
+            s.iter()
+                .try_fold(0u8, |n, &b| Some(n * 16 + (b as char).to_digit(16)? as u8))
+        };
+        if let Some(n) = s.strip_prefix(b"ansi:") {
+            let n = n.iter().try_fold(0u16, |n, &b| match b {
+                b'0'..=b'9' => Some(n * 10 + (b - b'0') as u16).filter(|&n| n <= 255),
+                _ => None,
+            });
+            return n.filter(|_| s.len() > 5).map(|n| Color::Ansi(n as _));
+        }
+        if let Some(name) = s.strip_prefix(b"bright-") {
+            return Literal::parse(name).map(Color::Bright);
+        }
+        Some(match s {
+            b"default" => Color::Clear,
+            [b'#', rgb @ ..] if rgb.len() == 6 => Color::Rgb {
+                r: hex(&rgb[0..2])?,
+                g: hex(&rgb[2..4])?,
+                b: hex(&rgb[4..6])?,
+            },
+            name => Color::Normal(Literal::parse(name)?),
+        })
+    }
+
     #[must_use]
     pub const fn ansi_sequence_fg(self, buf: &mut [u8; COLOR_SEQUENCE_SISE]) -> usize {
         let mut writer = io::ArrayWriter::new(buf);
@@ -91,3 +138,28 @@ impl Color {
         writer.len
     }
 }
+
+#[test]
+fn test_parse() {
+    assert_eq!(Color::parse(b"red"), Some(Color::Normal(Literal::Red)));
+    assert_eq!(
+        Color::parse(b"bright-blue"),
+        Some(Color::Bright(Literal::Blue))
+    );
+    assert_eq!(Color::parse(b"default"), Some(Color::Clear));
+    assert_eq!(Color::parse(b"ansi:208"), Some(Color::Ansi(208)));
+    assert_eq!(
+        Color::parse(b"#ff8000"),
+        Some(Color::Rgb {
+            r: 255,
+            g: 128,
+            b: 0
+        })
+    );
+    assert_eq!(Color::parse(b"ansi:256"), None);
+    assert_eq!(Color::parse(b"ansi:"), None);
+    assert_eq!(Color::parse(b"#ff800"), None);
+    assert_eq!(Color::parse(b"#gg8000"), None);
+    assert_eq!(Color::parse(b"bright-"), None);
+    assert_eq!(Color::parse(b"purple"), None);
+}
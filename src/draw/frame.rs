@@ -0,0 +1,126 @@
+use crate::draw::Element;
+
+/// Largest frame; a bigger terminal keeps the clock in its top left part.
+pub const MAX_COLUMNS: usize = 512;
+pub const MAX_LINES: usize = 256;
+
+/// Stands for `█` in a cell.
+pub const FULL_BLOCK: u8 = 0;
+
+/// A byte of text or `FULL_BLOCK`, in the colors of an element.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub c: u8,
+    pub element: Element,
+}
+
+impl Cell {
+    pub const BLANK: Self = Self {
+        c: b' ',
+        element: Element::Screen,
+    };
+}
+
+/// Most runs in a frame, enough for every zone with its date and label.
+const MAX_RUNS: usize = 2048;
+
+/// Cells from `column` on, `len` wide and from `line` to `last` high, all alike.
+#[derive(Clone, Copy)]
+struct Run {
+    line: u8,
+    last: u8,
+    column: u16,
+    len: u16,
+    cell: Cell,
+}
+
+/// What the screen shows, or is going to: runs of cells on a blank screen,
+/// each drawn over those before it.
+pub struct Frame {
+    pub columns: usize,
+    pub lines: usize,
+    runs: [Run; MAX_RUNS],
+    run_count: usize,
+    /// A bit per line that some run is on.
+    drawn: [u64; MAX_LINES / 64],
+}
+
+impl Frame {
+    /// Resizes to `columns` by `lines`, at most the maximum, all blank.
+    pub fn clear(&mut self, columns: usize, lines: usize) {
+        self.columns = columns.min(MAX_COLUMNS);
+        self.lines = lines.min(MAX_LINES);
+        self.run_count = 0;
+        self.drawn = [0; MAX_LINES / 64];
+    }
+
+    /// The cells of `line`, put together in `cells`.
+    pub fn line<'a>(&self, line: usize, cells: &'a mut [Cell; MAX_COLUMNS]) -> &'a [Cell] {
+        let cells = &mut cells[..self.columns];
+        cells.fill(Cell::BLANK);
+        if self.drawn[line / 64] & 1 << (line % 64) != 0 {
+            for run in &self.runs[..self.run_count] {
+                if (run.line..=run.last).contains(&(line as u8)) {
+                    cells[run.column as usize..][..run.len as usize].fill(run.cell);
+                }
+            }
+        }
+        cells
+    }
+
+    /// Sets `n` cells from `column` on, dropping those off the frame.
+    pub fn fill(&mut self, line: usize, column: usize, n: usize, cell: Cell) {
+        self.fill_lines(line, 1, column, n, cell);
+    }
+
+    /// Sets `n` cells from `column` on in each of `lines` lines from `line`.
+    pub fn fill_lines(&mut self, line: usize, lines: usize, column: usize, n: usize, cell: Cell) {
+        if line >= self.lines || column >= self.columns || lines == 0 || n == 0 {
+            return;
+        }
+        let run = Run {
+            line: line as _,
+            last: (line + lines.min(self.lines - line) - 1) as _,
+            column: column as _,
+            len: n.min(self.columns - column) as _,
+            cell,
+        };
+        // what carries on the last run lengthens it
+        if let Some(last) = self.runs[..self.run_count].last_mut()
+            && (last.line, last.last, last.cell) == (run.line, run.last, run.cell)
+            && last.column + last.len == run.column
+        {
+            last.len += run.len;
+            return;
+        }
+        if self.run_count == MAX_RUNS {
+            return;
+        }
+        self.runs[self.run_count] = run;
+        self.run_count += 1;
+        for line in line..=run.last as usize {
+            self.drawn[line / 64] |= 1 << (line % 64);
+        }
+    }
+
+    /// Sets a cell per byte of `text` from `column` on.
+    pub fn text(&mut self, line: usize, column: usize, text: &[u8], element: Element) {
+        for (i, &c) in text.iter().enumerate() {
+            self.fill(line, column + i, 1, Cell { c, element });
+        }
+    }
+}
+
+#[test]
+fn test_clip() {
+    static mut FRAME: core::mem::MaybeUninit<Frame> = core::mem::MaybeUninit::uninit();
+    #[allow(static_mut_refs)]
+    let frame = unsafe { FRAME.assume_init_mut() };
+    frame.clear(4, 2);
+    frame.text(1, 2, b"abc", Element::Labels);
+    frame.fill(2, 0, 4, Cell::BLANK);
+    let text =
+        |line| core::array::from_fn(|i| frame.line(line, &mut [Cell::BLANK; MAX_COLUMNS])[i].c);
+    assert_eq!(text(0), *b"    ");
+    assert_eq!(text(1), *b"  ab");
+}
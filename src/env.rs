@@ -0,0 +1,62 @@
+use core::ptr::null;
+
+static mut ARGV: *const *const u8 = null();
+static mut ENVP: *const *const u8 = null();
+
+/// Records the arguments and environment from the initial process stack,
+/// laid out by the kernel as `argc, argv[0..argc], NULL, envp[..], NULL`.
+///
+/// # Safety
+/// `sp` must be the initial stack pointer, and this must run once, before
+/// anything reads the arguments or environment.
+pub unsafe fn init(sp: *const usize) {
+    unsafe {
+        let argc = *sp;
+        ARGV = sp.add(1) as _;
+        ENVP = sp.add(argc + 2) as _;
+    }
+}
+
+/// # Safety
+/// `ptr` must point to a NUL-terminated string that lives for the rest of the program.
+pub unsafe fn c_str(ptr: *const u8) -> &'static [u8] {
+    let mut len = 0;
+    while unsafe { *ptr.add(len) } != 0 {
+        len += 1;
+    }
+    unsafe { core::slice::from_raw_parts(ptr, len) }
+}
+
+/// Iterates a NULL-terminated array of C strings.
+fn strings(mut ptr: *const *const u8) -> impl Iterator<Item = &'static [u8]> {
+    core::iter::from_fn(move || unsafe {
+        if ptr.is_null() || (*ptr).is_null() {
+            return None;
+        }
+        let s = c_str(*ptr);
+        ptr = ptr.add(1);
+        Some(s)
+    })
+}
+
+pub fn args() -> impl Iterator<Item = &'static [u8]> {
+    strings(unsafe { ARGV })
+}
+
+pub fn vars() -> impl Iterator<Item = &'static [u8]> {
+    strings(unsafe { ENVP })
+}
+
+pub fn var(name: &[u8]) -> Option<&'static [u8]> {
+    vars().find_map(|var| match var.strip_prefix(name) {
+        Some([b'=', value @ ..]) => Some(value),
+        _ => None,
+    })
+}
+
+/// The locale of `category` such as `LC_TIME`: `LC_ALL`, the category, then `LANG`.
+pub fn locale(category: &[u8]) -> Option<&'static [u8]> {
+    [&b"LC_ALL"[..], category, b"LANG"]
+        .into_iter()
+        .find_map(|name| var(name).filter(|value| !value.is_empty()))
+}
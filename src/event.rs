@@ -0,0 +1,391 @@
+use core::cell::Cell;
+
+use crate::{
+    io,
+    io_uring::IoUring,
+    time::{self, Itimerspec, Timespec},
+};
+
+/// Operations in flight at once on the epoll backend.
+const MAX_PENDING: usize = 8;
+
+// timeout flags beside `nc::IORING_TIMEOUT_ABS`, which nc 0.9 lacks
+/// Times on `CLOCK_REALTIME`, so that absolute timeouts follow clock changes.
+pub const IORING_TIMEOUT_REALTIME: u32 = 1 << 3;
+/// Fires every interval until removed.
+pub const IORING_TIMEOUT_MULTISHOT: u32 = 1 << 6;
+
+// nc's structs must lay out as the kernel's on 32-bit targets too, where
+// `u64` may be 4-aligned and `timespec` has 32-bit seconds
+const _: () = {
+    use core::mem::offset_of;
+    use nc::{io_uring_cqe_t, io_uring_sqe_t, kernel_timespec_t};
+    assert!(size_of::<io_uring_sqe_t>() == 64);
+    assert!(offset_of!(io_uring_sqe_t, fd) == 4);
+    assert!(offset_of!(io_uring_sqe_t, file_off) == 8);
+    assert!(offset_of!(io_uring_sqe_t, buf_addr) == 16);
+    assert!(offset_of!(io_uring_sqe_t, len) == 24);
+    assert!(offset_of!(io_uring_sqe_t, other_flags) == 28);
+    assert!(offset_of!(io_uring_sqe_t, user_data) == 32);
+    assert!(size_of::<io_uring_cqe_t>() == 16);
+    assert!(offset_of!(io_uring_cqe_t, user_data) == 0);
+    assert!(offset_of!(io_uring_cqe_t, res) == 8);
+    assert!(size_of::<kernel_timespec_t>() == 16);
+    assert!(offset_of!(kernel_timespec_t, tv_nsec) == 8);
+};
+
+/// A finished operation: the `user_data` it was prepared with, and a byte
+/// count or a negated errno, as io_uring reports them.
+#[derive(Clone, Copy, Default)]
+pub struct Completion {
+    pub user_data: u64,
+    pub res: i32,
+}
+
+/// Reads and timeouts through io_uring, or through epoll and timerfds where
+/// io_uring is disabled.
+// one per process and nothing to box it in without an allocator
+#[allow(clippy::large_enum_variant)]
+pub enum EventLoop {
+    IoUring(IoUring),
+    Epoll(Epoll),
+}
+
+impl EventLoop {
+    /// Takes io_uring unless the kernel or a seccomp filter refuses it.
+    pub fn new(entries: u32) -> io::Result<Self> {
+        match IoUring::new(entries as _) {
+            Ok(ring) => Ok(Self::IoUring(ring)),
+            Err(nc::EPERM | nc::ENOSYS) => Epoll::new().map(Self::Epoll),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// `buf` must stay put until the read completes.
+    pub fn prepare_read(&self, fd: u32, buf: &mut [u8], user_data: u64) {
+        match self {
+            Self::IoUring(ring) => ring.prepare_read(fd as _, buf, user_data),
+            Self::Epoll(epoll) => epoll.prepare_read(fd as _, buf, user_data),
+        }
+    }
+
+    /// Takes io_uring's timeout flags: `IORING_TIMEOUT_ABS`,
+    /// `IORING_TIMEOUT_REALTIME` and `IORING_TIMEOUT_MULTISHOT`.
+    /// `ts` must stay put until the next `submit`.
+    pub fn prepare_timeout(&self, ts: &nc::kernel_timespec_t, user_data: u64, flags: u32) {
+        match self {
+            Self::IoUring(ring) => ring.prepare_timeout(ts, user_data, flags as _),
+            Self::Epoll(epoll) => {
+                let ts = Timespec {
+                    tv_sec: ts.tv_sec,
+                    tv_nsec: ts.tv_nsec,
+                };
+                epoll.prepare_timeout(&ts, user_data, flags)
+            }
+        }
+    }
+
+    /// Cancels the timeout prepared with `target`, which then completes with
+    /// `ECANCELED`. This completes with 0, or `ENOENT` if it had already fired.
+    pub fn prepare_timeout_remove(&self, target: u64, user_data: u64) {
+        match self {
+            Self::IoUring(ring) => ring.prepare_timeout_remove(target, user_data),
+            Self::Epoll(epoll) => epoll.prepare_timeout_remove(target, user_data),
+        }
+    }
+
+    /// Starts the last `n` operations prepared; epoll starts them right away.
+    pub fn submit(&self, n: u32) -> io::Result<()> {
+        match self {
+            Self::IoUring(ring) => ring.submit(n as _).map(drop),
+            Self::Epoll(_) => Ok(()),
+        }
+    }
+
+    /// Blocks until something completes, failing with `EINTR` on a signal.
+    pub fn wait(&self) -> io::Result<()> {
+        match self {
+            Self::IoUring(ring) => ring.wait().map(drop),
+            Self::Epoll(epoll) => epoll.wait(),
+        }
+    }
+
+    /// Takes the oldest completion, once `wait` has returned.
+    pub fn complete(&self) -> Completion {
+        match self {
+            Self::IoUring(ring) => {
+                let cqe = ring.complete();
+                Completion {
+                    user_data: cqe.user_data,
+                    res: cqe.res,
+                }
+            }
+            Self::Epoll(epoll) => epoll.complete(),
+        }
+    }
+}
+
+/// `struct epoll_event`, which is packed on x86_64.
+#[cfg_attr(target_arch = "x86_64", repr(C, packed))]
+#[cfg_attr(not(target_arch = "x86_64"), repr(C))]
+#[derive(Clone, Copy, Default)]
+struct EpollEvent {
+    events: u32,
+    data: u64,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Read {
+        fd: i32,
+        buf: *mut u8,
+        len: usize,
+    },
+    /// Read from `timer` once it expires; repeating ones stay armed.
+    Timeout {
+        timer: i32,
+        repeat: bool,
+    },
+}
+
+#[derive(Clone, Copy)]
+struct Pending {
+    op: Op,
+    user_data: u64,
+}
+
+/// Readiness of each fd read, then the read itself. Timeouts are timerfds,
+/// one per clock, and a new timeout replaces the one pending.
+pub struct Epoll {
+    fd: i32,
+    realtime: i32,
+    monotonic: i32,
+    /// Indexed by the data given to epoll.
+    pending: Cell<[Option<Pending>; MAX_PENDING]>,
+    completions: Cell<[Completion; MAX_PENDING]>,
+    completion_count: Cell<usize>,
+}
+
+impl Epoll {
+    pub fn new() -> io::Result<Self> {
+        unsafe {
+            let timer = |clock| nc::timerfd_create(clock, nc::TFD_CLOEXEC);
+            Ok(Self {
+                fd: nc::epoll_create1(nc::EPOLL_CLOEXEC)?,
+                realtime: timer(nc::CLOCK_REALTIME)?,
+                monotonic: timer(nc::CLOCK_MONOTONIC)?,
+                pending: Cell::new([None; MAX_PENDING]),
+                completions: Cell::new([Completion::default(); MAX_PENDING]),
+                completion_count: Cell::new(0),
+            })
+        }
+    }
+
+    fn push_completion(&self, user_data: u64, res: i32) {
+        let mut completions = self.completions.get();
+        let count = self.completion_count.get();
+        if count < MAX_PENDING {
+            completions[count] = Completion { user_data, res };
+            self.completions.set(completions);
+            self.completion_count.set(count + 1);
+        }
+    }
+
+    /// Waits for `fd` to be readable, then for `op`; errors complete at once.
+    fn start(&self, fd: i32, events: u32, op: Op, user_data: u64) {
+        let mut pending = self.pending.get();
+        // a new timeout takes the place of the pending one, wherever it is
+        let replaced = match op {
+            Op::Timeout { .. } => pending.iter().position(|p| {
+                matches!(
+                    p,
+                    Some(Pending {
+                        op: Op::Timeout { .. },
+                        ..
+                    })
+                )
+            }),
+            Op::Read { .. } => None,
+        };
+        let slot = replaced.or_else(|| pending.iter().position(Option::is_none));
+        let Some(slot) = slot else {
+            return self.push_completion(user_data, -nc::EBUSY);
+        };
+        pending[slot] = Some(Pending { op, user_data });
+        let mut event = EpollEvent {
+            events,
+            data: slot as _,
+        };
+        let mut ctl = |op: i32| unsafe {
+            nc::syscalls::syscall4(
+                nc::SYS_EPOLL_CTL,
+                self.fd as _,
+                op as _,
+                fd as _,
+                &raw mut event as _,
+            )
+        };
+        // fds stay added once they were, disabled after a one-shot event
+        let result = match ctl(nc::EPOLL_CTL_MOD) {
+            Err(nc::ENOENT) => ctl(nc::EPOLL_CTL_ADD),
+            result => result,
+        };
+        match result {
+            Ok(_) => self.pending.set(pending),
+            Err(e) => self.push_completion(user_data, -e),
+        }
+    }
+
+    fn prepare_read(&self, fd: i32, buf: &mut [u8], user_data: u64) {
+        let op = Op::Read {
+            fd,
+            buf: buf.as_mut_ptr(),
+            len: buf.len(),
+        };
+        self.start(fd, nc::EPOLLIN | nc::EPOLLONESHOT, op, user_data);
+    }
+
+    fn prepare_timeout(&self, ts: &Timespec, user_data: u64, flags: u32) {
+        let (timer, other) = match flags & IORING_TIMEOUT_REALTIME {
+            0 => (self.monotonic, self.realtime),
+            _ => (self.realtime, self.monotonic),
+        };
+        let repeat = flags & IORING_TIMEOUT_MULTISHOT != 0;
+        let spec = Itimerspec {
+            it_interval: if repeat { *ts } else { Default::default() },
+            it_value: *ts,
+        };
+        let abs = match flags & nc::IORING_TIMEOUT_ABS {
+            0 => 0,
+            _ => nc::TFD_TIMER_ABSTIME,
+        };
+        let result = time::timerfd_settime(other, 0, &Default::default())
+            .and_then(|_| time::timerfd_settime(timer, abs, &spec));
+        match result {
+            Ok(()) => {
+                let events = if repeat {
+                    nc::EPOLLIN
+                } else {
+                    nc::EPOLLIN | nc::EPOLLONESHOT
+                };
+                self.start(timer, events, Op::Timeout { timer, repeat }, user_data);
+            }
+            Err(e) => self.push_completion(user_data, -e),
+        }
+    }
+
+    fn prepare_timeout_remove(&self, target: u64, user_data: u64) {
+        let mut pending = self.pending.get();
+        let timer = pending.iter_mut().find_map(|p| match *p {
+            Some(Pending {
+                op: Op::Timeout { timer, .. },
+                user_data,
+            }) if user_data == target => {
+                *p = None;
+                Some(timer)
+            }
+            _ => None,
+        });
+        let Some(timer) = timer else {
+            return self.push_completion(user_data, -nc::ENOENT);
+        };
+        // disarming also drops an expiry not yet waited for
+        match time::timerfd_settime(timer, 0, &Default::default()) {
+            Ok(()) => {
+                self.pending.set(pending);
+                self.push_completion(target, -nc::ECANCELED);
+                self.push_completion(user_data, 0);
+            }
+            Err(e) => self.push_completion(user_data, -e),
+        }
+    }
+
+    fn wait(&self) -> io::Result<()> {
+        // events for operations replaced meanwhile complete nothing
+        while self.completion_count.get() == 0 {
+            self.wait_events()?;
+        }
+        Ok(())
+    }
+
+    fn wait_events(&self) -> io::Result<()> {
+        let mut events = [EpollEvent::default(); MAX_PENDING];
+        // `epoll_wait` where there is one: nc 0.9's `syscall6` on x86 swaps
+        // arguments through registers it only declares as inputs
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm"))]
+        let n = unsafe {
+            nc::syscalls::syscall4(
+                nc::SYS_EPOLL_WAIT,
+                self.fd as _,
+                events.as_mut_ptr() as _,
+                MAX_PENDING,
+                -1isize as _,
+            )?
+        };
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm")))]
+        let n = unsafe {
+            nc::syscalls::syscall6(
+                nc::SYS_EPOLL_PWAIT,
+                self.fd as _,
+                events.as_mut_ptr() as _,
+                MAX_PENDING,
+                -1isize as _,
+                0,
+                0,
+            )?
+        };
+        for event in &events[..n] {
+            let slot = event.data as usize;
+            let mut pending = self.pending.get();
+            let Some(Pending { op, user_data }) = pending[slot] else {
+                continue;
+            };
+            let res = match op {
+                Op::Read { fd, buf, len } => {
+                    pending[slot] = None;
+                    let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+                    unsafe { nc::read(fd, buf) }.map_or_else(|e| -e, |n| n as _)
+                }
+                Op::Timeout { timer, repeat } => {
+                    if !repeat {
+                        pending[slot] = None;
+                    }
+                    let mut expirations = [0; 8];
+                    _ = unsafe { nc::read(timer, &mut expirations) };
+                    -nc::ETIME
+                }
+            };
+            self.pending.set(pending);
+            self.push_completion(user_data, res);
+        }
+        Ok(())
+    }
+
+    fn complete(&self) -> Completion {
+        let mut completions = self.completions.get();
+        let first = completions[0];
+        completions.copy_within(1.., 0);
+        self.completions.set(completions);
+        self.completion_count
+            .set(self.completion_count.get().saturating_sub(1));
+        first
+    }
+}
+
+#[test]
+fn test_timeout_remove() {
+    let epoll = Epoll::new().unwrap();
+    let minute = Timespec {
+        tv_sec: 60,
+        tv_nsec: 0,
+    };
+    epoll.prepare_timeout(&minute, 1, 0);
+    epoll.prepare_timeout_remove(1, 2);
+    epoll.prepare_timeout_remove(1, 3);
+    epoll.wait().unwrap();
+    let completions = [(); 3].map(|_| {
+        let Completion { user_data, res } = epoll.complete();
+        (user_data, res)
+    });
+    assert_eq!(completions, [(1, -nc::ECANCELED), (2, 0), (3, -nc::ENOENT)]);
+}
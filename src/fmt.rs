@@ -0,0 +1,411 @@
+use core::fmt::{self, Display, Write};
+
+/// Displays a byte string, replacing invalid UTF-8 with U+FFFD.
+pub struct Bytes<'a>(pub &'a [u8]);
+
+impl Display for Bytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.0.utf8_chunks() {
+            f.write_str(chunk.valid())?;
+            if !chunk.invalid().is_empty() {
+                f.write_char(char::REPLACEMENT_CHARACTER)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A fixed buffer to format into without an allocator, dropping what doesn't fit.
+pub struct Buf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Default for Buf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Buf<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Appends what fits of `bytes`.
+    pub fn push(&mut self, bytes: &[u8]) {
+        let len = bytes.len().min(N - self.len);
+        self.buf[self.len..][..len].copy_from_slice(&bytes[..len]);
+        self.len += len;
+    }
+
+    /// Appends `bytes`, replacing invalid UTF-8 with U+FFFD.
+    pub fn push_lossy(&mut self, bytes: &[u8]) {
+        for chunk in bytes.utf8_chunks() {
+            _ = self.write_str(chunk.valid());
+            if !chunk.invalid().is_empty() {
+                _ = self.write_str("\u{fffd}");
+            }
+        }
+    }
+
+    /// Appends `n` in decimal, padded to `width` with `fill` like `{n:0width$}`
+    /// or `{n:width$}` would.
+    pub fn push_number(&mut self, n: i64, width: usize, fill: u8) {
+        let mut digits = [0; 20];
+        let mut start = digits.len();
+        let mut rest = n.unsigned_abs();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (rest % 10) as u8;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        let sign: &[u8] = if n < 0 { b"-" } else { b"" };
+        let len = sign.len() + digits.len() - start;
+        if fill == b'0' {
+            self.push(sign);
+        }
+        for _ in len..width {
+            self.push(&[fill]);
+        }
+        if fill != b'0' {
+            self.push(sign);
+        }
+        self.push(&digits[start..]);
+    }
+}
+
+impl<const N: usize> Write for Buf<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(N - self.len);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+const DAY: i64 = 86400;
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+pub const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// `(year, month, day)` of a number of days since 1970-01-01.
+pub const fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month as _, day as _)
+}
+
+pub const fn is_leap(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+/// Broken-down local time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DateTime {
+    pub year: i64,
+    /// 1 to 12.
+    pub month: u8,
+    /// 1 to 31.
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// 0 = Sunday.
+    pub weekday: u8,
+    /// 0-based day of the year.
+    pub yday: u16,
+}
+
+impl DateTime {
+    /// From seconds since the epoch, already shifted to local time.
+    pub const fn from_timestamp(t: i64) -> Self {
+        let days = t.div_euclid(DAY);
+        let secs = t.rem_euclid(DAY);
+        let (year, month, day) = civil_from_days(days);
+        Self {
+            year,
+            month,
+            day,
+            hour: (secs / 3600) as _,
+            minute: (secs / 60 % 60) as _,
+            second: (secs % 60) as _,
+            // 1970-01-01 was a Thursday
+            weekday: (days + 4).rem_euclid(7) as _,
+            yday: (days - days_from_civil(year, 1, 1)) as _,
+        }
+    }
+
+    /// ISO 8601 week-based year and week number.
+    pub const fn iso_week(&self) -> (i64, u8) {
+        // 53 weeks when the year ends on a Thursday, or the previous one on a Wednesday
+        const fn p(y: i64) -> i64 {
+            (y + y.div_euclid(4) - y.div_euclid(100) + y.div_euclid(400)).rem_euclid(7)
+        }
+        const fn weeks(year: i64) -> i64 {
+            52 + (p(year) == 4 || p(year - 1) == 3) as i64
+        }
+        let iso_weekday = (self.weekday as i64 + 6) % 7 + 1;
+        let week = (self.yday as i64 + 1 - iso_weekday + 10) / 7;
+        if week < 1 {
+            (self.year - 1, weeks(self.year - 1) as _)
+        } else if week > weeks(self.year) {
+            (self.year + 1, 1)
+        } else {
+            (self.year, week as _)
+        }
+    }
+}
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Formats a time like strftime(3), in the C locale.
+///
+/// Supports `%a %A %b %h %B %C %d %D %e %F %G %g %H %I %j %m %M %p %R %s %S
+/// %T %u %U %V %w %W %y %Y %z %%`, and `-` to drop padding as in `%-d`.
+/// Anything else is written as is.
+pub struct Strftime<'a> {
+    pub format: &'a [u8],
+    /// Seconds since the epoch.
+    pub time: i64,
+    pub utc_offset: i32,
+}
+
+impl Strftime<'_> {
+    /// Writes the time into `buf` the way `format` says, without `core::fmt`
+    /// as it runs on every redraw.
+    pub fn write_to<const N: usize>(&self, buf: &mut Buf<N>) {
+        let t = DateTime::from_timestamp(self.time + self.utc_offset as i64);
+        let mut format = self.format;
+        while let Some(i) = format.iter().position(|&b| b == b'%') {
+            buf.push_lossy(&format[..i]);
+            format = &format[i + 1..];
+            let pad = !format.starts_with(b"-");
+            if !pad {
+                format = &format[1..];
+            }
+            let Some((&spec, rest)) = format.split_first() else {
+                return buf.push(if pad { b"%" } else { b"%-" });
+            };
+            format = rest;
+            // `-` drops the padding of numbers
+            let width = |width| if pad { width } else { 0 };
+            let hour12 = (t.hour as i64 + 11) % 12 + 1;
+            let (iso_year, iso_week) = t.iso_week();
+            let weekday = WEEKDAYS[t.weekday as usize].as_bytes();
+            let month = MONTHS[t.month as usize - 1].as_bytes();
+            match spec {
+                b'a' => buf.push(&weekday[..3]),
+                b'A' => buf.push(weekday),
+                b'b' | b'h' => buf.push(&month[..3]),
+                b'B' => buf.push(month),
+                b'C' => buf.push_number(t.year.div_euclid(100), width(2), b'0'),
+                b'd' => buf.push_number(t.day as _, width(2), b'0'),
+                b'D' => {
+                    buf.push_number(t.month as _, 2, b'0');
+                    buf.push(b"/");
+                    buf.push_number(t.day as _, 2, b'0');
+                    buf.push(b"/");
+                    buf.push_number(t.year.rem_euclid(100), 2, b'0');
+                }
+                b'e' => buf.push_number(t.day as _, width(2), b' '),
+                b'F' => {
+                    buf.push_number(t.year, 0, b'0');
+                    buf.push(b"-");
+                    buf.push_number(t.month as _, 2, b'0');
+                    buf.push(b"-");
+                    buf.push_number(t.day as _, 2, b'0');
+                }
+                b'G' => buf.push_number(iso_year, 0, b'0'),
+                b'g' => buf.push_number(iso_year.rem_euclid(100), width(2), b'0'),
+                b'H' => buf.push_number(t.hour as _, width(2), b'0'),
+                b'I' => buf.push_number(hour12, width(2), b'0'),
+                b'j' => buf.push_number(t.yday as i64 + 1, width(3), b'0'),
+                b'm' => buf.push_number(t.month as _, width(2), b'0'),
+                b'M' => buf.push_number(t.minute as _, width(2), b'0'),
+                b'p' => buf.push(if t.hour < 12 { b"AM" } else { b"PM" }),
+                b'R' => {
+                    buf.push_number(t.hour as _, 2, b'0');
+                    buf.push(b":");
+                    buf.push_number(t.minute as _, 2, b'0');
+                }
+                b's' => buf.push_number(self.time, 0, b'0'),
+                b'S' => buf.push_number(t.second as _, width(2), b'0'),
+                b'T' => {
+                    buf.push_number(t.hour as _, 2, b'0');
+                    buf.push(b":");
+                    buf.push_number(t.minute as _, 2, b'0');
+                    buf.push(b":");
+                    buf.push_number(t.second as _, 2, b'0');
+                }
+                b'u' => buf.push_number((t.weekday as i64 + 6) % 7 + 1, 0, b'0'),
+                b'U' => buf.push_number((t.yday as i64 + 7 - t.weekday as i64) / 7, width(2), b'0'),
+                b'V' => buf.push_number(iso_week as _, width(2), b'0'),
+                b'w' => buf.push_number(t.weekday as _, 0, b'0'),
+                b'W' => buf.push_number(
+                    (t.yday as i64 + 7 - (t.weekday as i64 + 6) % 7) / 7,
+                    width(2),
+                    b'0',
+                ),
+                b'y' => buf.push_number(t.year.rem_euclid(100), width(2), b'0'),
+                b'Y' => buf.push_number(t.year, 0, b'0'),
+                b'z' => {
+                    let minutes = self.utc_offset.unsigned_abs() / 60;
+                    buf.push(if self.utc_offset < 0 { b"-" } else { b"+" });
+                    buf.push_number((minutes / 60) as _, 2, b'0');
+                    buf.push_number((minutes % 60) as _, 2, b'0');
+                }
+                b'%' => buf.push(b"%"),
+                _ => {
+                    buf.push(if pad { b"%" } else { b"%-" });
+                    buf.push_lossy(&[spec]);
+                }
+            }
+        }
+        buf.push_lossy(format);
+    }
+}
+
+/// Whether a locale such as `en_US.UTF-8` conventionally shows a 12-hour clock.
+pub fn twelve_hour_locale(locale: &[u8]) -> bool {
+    const LOCALES: &[&[u8]] = &[
+        b"en_US", b"en_CA", b"en_AU", b"en_NZ", b"en_PH", b"en_IN", b"es_US", b"hi_IN", b"ur_PK",
+        b"bn_BD", b"ar_EG", b"ar_SA",
+    ];
+    let name = locale.split(|&b| b == b'.' || b == b'@').next();
+    name.is_some_and(|name| LOCALES.contains(&name))
+}
+
+#[cfg(test)]
+fn strftime(format: &[u8], time: i64, utc_offset: i32) -> Buf<64> {
+    let mut buf = Buf::new();
+    Strftime {
+        format,
+        time,
+        utc_offset,
+    }
+    .write_to(&mut buf);
+    buf
+}
+
+#[test]
+fn test_civil() {
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    assert_eq!(civil_from_days(days_from_civil(2028, 2, 29)), (2028, 2, 29));
+    // 2026-10-18 12:34:56 UTC
+    let t = DateTime::from_timestamp(1792326896);
+    assert_eq!((t.year, t.month, t.day), (2026, 10, 18));
+    assert_eq!(
+        (t.hour, t.minute, t.second, t.weekday, t.yday),
+        (12, 34, 56, 0, 290)
+    );
+}
+
+#[test]
+fn test_iso_week() {
+    let week = |y, m, d| DateTime::from_timestamp(days_from_civil(y, m, d) * DAY).iso_week();
+    assert_eq!(week(2026, 10, 18), (2026, 42));
+    assert_eq!(week(2021, 1, 3), (2020, 53));
+    assert_eq!(week(2024, 12, 30), (2025, 1));
+    assert_eq!(week(2026, 1, 1), (2026, 1));
+}
+
+#[test]
+fn test_strftime() {
+    const T: i64 = 1792326896;
+    assert_eq!(
+        strftime(b"%a %d %b %Y", T, 0).as_bytes(),
+        b"Sun 18 Oct 2026"
+    );
+    assert_eq!(
+        strftime(b"%G-W%V-%u, %j", T, 0).as_bytes(),
+        b"2026-W42-7, 291"
+    );
+    assert_eq!(
+        strftime(b"%F %T %z", T, -16200).as_bytes(),
+        b"2026-10-18 08:04:56 -0430"
+    );
+    assert_eq!(
+        strftime(b"%-d.%-m. %e %I%p", T, 3600).as_bytes(),
+        b"18.10. 18 01PM"
+    );
+    assert_eq!(strftime(b"100%% %q %", T, 0).as_bytes(), b"100% %q %");
+    assert_eq!(
+        strftime(b"%A %B %A %B", T, 0).as_bytes(),
+        b"Sunday October Sunday October"
+    );
+}
+
+#[test]
+fn test_twelve_hour_locale() {
+    assert!(twelve_hour_locale(b"en_US.UTF-8"));
+    assert!(twelve_hour_locale(b"en_AU"));
+    assert!(!twelve_hour_locale(b"en_GB.UTF-8"));
+    assert!(!twelve_hour_locale(b"C"));
+    assert!(!twelve_hour_locale(b"de_DE@euro"));
+}
+
+#[test]
+fn test_push_number() {
+    let number = |n, width, fill| {
+        let mut buf = Buf::<8>::new();
+        buf.push_number(n, width, fill);
+        buf
+    };
+    assert_eq!(number(7, 2, b'0').as_bytes(), b"07");
+    assert_eq!(number(-7, 3, b'0').as_bytes(), b"-07");
+    assert_eq!(number(-7, 3, b' ').as_bytes(), b" -7");
+    assert_eq!(number(123, 2, b'0').as_bytes(), b"123");
+    assert_eq!(number(i64::MIN, 0, b'0').as_bytes(), b"-9223372");
+}
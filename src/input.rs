@@ -0,0 +1,303 @@
+use crate::keymap::{ALT, CTRL, Code, Key, SHIFT};
+
+const ESC: u8 = 0x1b;
+/// Longest input kept while waiting for the rest of it.
+const BUF_SIZE: usize = 64;
+
+pub enum Event<'a> {
+    Key(Key),
+    /// Whole input that is no key, such as a reply to a query.
+    Sequence(&'a [u8]),
+}
+
+/// Turns terminal input into keys: legacy CSI and SS3 sequences with xterm
+/// modifiers, the kitty keyboard protocol and UTF-8. What is cut short by the
+/// end of a read waits for the next one, or for `flush`.
+pub struct Decoder {
+    buf: [u8; BUF_SIZE],
+    /// Undecoded input is `buf[start..len]`.
+    start: usize,
+    len: usize,
+    /// Set by `flush` until all input is decoded.
+    flushing: bool,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; BUF_SIZE],
+            start: 0,
+            len: 0,
+            flushing: false,
+        }
+    }
+
+    pub fn push(&mut self, input: &[u8]) {
+        self.buf.copy_within(self.start..self.len, 0);
+        self.len -= self.start;
+        self.start = 0;
+        // nothing left waiting this long is the start of a sequence
+        if self.len + input.len() > BUF_SIZE {
+            self.len = 0;
+        }
+        let input = &input[..input.len().min(BUF_SIZE)];
+        self.buf[self.len..][..input.len()].copy_from_slice(input);
+        self.len += input.len();
+    }
+
+    /// Whether input waits for more, to be flushed if none comes.
+    pub fn is_waiting(&self) -> bool {
+        self.start < self.len
+    }
+
+    /// Takes the input waiting as it is: a lone ESC is the Esc key, and an
+    /// ESC before the start of a sequence is Alt.
+    pub fn flush(&mut self) {
+        self.flushing = true;
+    }
+
+    pub fn next_event(&mut self) -> Option<Event<'_>> {
+        let input = &self.buf[self.start..self.len];
+        let decoded = match decode(input) {
+            Some(decoded) => decoded,
+            None if input.is_empty() || !self.flushing => {
+                self.flushing = false;
+                return None;
+            }
+            None => match *input {
+                [ESC] => (Key::from_byte(ESC), 1),
+                [ESC, b] => (Key::from_byte(b).map(alt), 2),
+                // cut short
+                _ => (None, input.len()),
+            },
+        };
+        let (key, n) = decoded;
+        let start = self.start;
+        self.start += n;
+        Some(match key {
+            Some(key) => Event::Key(key),
+            None => Event::Sequence(&self.buf[start..start + n]),
+        })
+    }
+}
+
+fn alt(key: Key) -> Key {
+    Key::new(key.code, key.mods | ALT)
+}
+
+/// The key at the start of `input` if any, and its length; `None` when more
+/// input is needed to tell.
+fn decode(input: &[u8]) -> Option<(Option<Key>, usize)> {
+    match *input {
+        [] | [ESC] | [ESC, b'O'] => None,
+        [ESC, b'[', ref rest @ ..] => csi(rest).map(|(key, n)| (key, n + 2)),
+        [ESC, b'O', b, ..] => Some((ss3(b), 3)),
+        // Alt sends ESC first
+        [ESC, ref rest @ ..] => decode(rest).map(|(key, n)| (key.map(alt), n + 1)),
+        [b, ..] if b < 0x80 => Some((Key::from_byte(b), 1)),
+        [b, ref rest @ ..] => {
+            let len = match b {
+                0xc2..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf4 => 4,
+                _ => return Some((None, 1)),
+            };
+            let continued = rest.iter().take(len - 1).take_while(|&b| b & 0xc0 == 0x80);
+            if continued.count() < rest.len().min(len - 1) {
+                return Some((None, 1));
+            }
+            let c = core::str::from_utf8(input.get(..len)?).ok();
+            Some((
+                c.and_then(|c| c.chars().next())
+                    .map(|c| Key::new(Code::Char(c), 0)),
+                len,
+            ))
+        }
+    }
+}
+
+/// `ESC O` and a letter, as sent by keypads and arrows in application mode.
+fn ss3(b: u8) -> Option<Key> {
+    let code = match b {
+        b'A' => Code::Up,
+        b'B' => Code::Down,
+        b'C' => Code::Right,
+        b'D' => Code::Left,
+        b'H' => Code::Home,
+        b'F' => Code::End,
+        b'M' => Code::Enter,
+        b'P'..=b'S' => Code::F(b - b'P' + 1),
+        _ => return None,
+    };
+    Some(Key::new(code, 0))
+}
+
+/// What follows `CSI`: parameters separated by `;` with sub-parameters after
+/// `:`, then a final byte. Modifiers are the second parameter less one.
+fn csi(input: &[u8]) -> Option<(Option<Key>, usize)> {
+    let end = input.iter().position(|b| !(0x20..0x40).contains(b))?;
+    let (params, last) = (&input[..end], input[end]);
+    // broken off by something else, which is decoded on its own
+    if !(0x40..0x7f).contains(&last) {
+        return Some((None, end));
+    }
+    let len = end + 1;
+    // private parameters and intermediates are replies, not keys
+    if !params
+        .iter()
+        .all(|b| matches!(b, b'0'..=b'9' | b';' | b':'))
+    {
+        return Some((None, len));
+    }
+    let mut fields = params.split(|&b| b == b';');
+    let mut field = || {
+        let mut subs = fields.next().unwrap_or_default().split(|&b| b == b':');
+        [subs.next(), subs.next()].map(|sub| sub.and_then(number))
+    };
+    let [number, _] = field();
+    let [mods, event] = field();
+    // releases, only reported by the kitty protocol when asked for
+    if event == Some(3) {
+        return Some((None, len));
+    }
+    let code = match (last, number) {
+        (b'A', _) => Some(Code::Up),
+        (b'B', _) => Some(Code::Down),
+        (b'C', _) => Some(Code::Right),
+        (b'D', _) => Some(Code::Left),
+        (b'H', _) => Some(Code::Home),
+        (b'F', _) => Some(Code::End),
+        (b'P'..=b'S', _) => Some(Code::F(last - b'P' + 1)),
+        (b'Z', _) => return Some((Some(Key::new(Code::Tab, SHIFT)), len)),
+        (b'~', Some(1 | 7)) => Some(Code::Home),
+        (b'~', Some(2)) => Some(Code::Insert),
+        (b'~', Some(3)) => Some(Code::Delete),
+        (b'~', Some(4 | 8)) => Some(Code::End),
+        (b'~', Some(5)) => Some(Code::PageUp),
+        (b'~', Some(6)) => Some(Code::PageDown),
+        (b'~', Some(n @ 11..=15)) => Some(Code::F(n as u8 - 10)),
+        (b'~', Some(n @ 17..=21)) => Some(Code::F(n as u8 - 11)),
+        (b'~', Some(n @ 23..=26)) => Some(Code::F(n as u8 - 12)),
+        (b'~', Some(n @ 28..=29)) => Some(Code::F(n as u8 - 13)),
+        (b'~', Some(n @ 31..=34)) => Some(Code::F(n as u8 - 14)),
+        // xterm's modifyOtherKeys, with the character last
+        (b'~', Some(27)) => field()[0].and_then(unicode),
+        // kitty's, with the character first
+        (b'u', Some(n)) => unicode(n),
+        _ => None,
+    };
+    let mods = (mods.unwrap_or(1).saturating_sub(1) as u8) & (SHIFT | ALT | CTRL);
+    Some((code.map(|code| Key::new(code, mods)), len))
+}
+
+fn number(digits: &[u8]) -> Option<u32> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.iter().try_fold(0u32, |n, &d| {
+        n.checked_mul(10)?.checked_add((d as char).to_digit(10)?)
+    })
+}
+
+/// A key by its code point, kitty's other keys being in the private use area.
+fn unicode(n: u32) -> Option<Code> {
+    match n {
+        8 | 127 => Some(Code::Backspace),
+        9 => Some(Code::Tab),
+        13 | 57414 => Some(Code::Enter),
+        27 => Some(Code::Esc),
+        57376..=57398 => Some(Code::F((n - 57376 + 13) as u8)),
+        _ => char::from_u32(n)
+            .filter(|c| !c.is_control())
+            .map(Code::Char),
+    }
+}
+
+#[cfg(test)]
+fn keys(decoder: &mut Decoder, input: &[u8]) -> ([Option<Key>; 8], usize) {
+    decoder.push(input);
+    let mut keys = [None; 8];
+    let mut count = 0;
+    while let Some(event) = decoder.next_event() {
+        if let Event::Key(key) = event {
+            keys[count] = Some(key);
+        }
+        count += 1;
+    }
+    (keys, count)
+}
+
+#[test]
+fn test_keys() {
+    let key = |input: &[u8]| {
+        let (keys, count) = keys(&mut Decoder::new(), input);
+        assert_eq!(count, 1, "{input:?}");
+        keys[0]
+    };
+    let name = |name| Key::parse(name);
+    assert_eq!(key(b"q"), name(b"q"));
+    assert_eq!(key(b"\x03"), name(b"ctrl-c"));
+    assert_eq!(key("é".as_bytes()), name("é".as_bytes()));
+    assert_eq!(key(b"\x1bx"), name(b"alt-x"));
+    assert_eq!(key(b"\x1b[A"), name(b"up"));
+    assert_eq!(key(b"\x1bOD"), name(b"left"));
+    assert_eq!(key(b"\x1bOQ"), name(b"f2"));
+    assert_eq!(key(b"\x1b[1;5C"), name(b"ctrl-right"));
+    assert_eq!(key(b"\x1b\x1b[B"), name(b"alt-down"));
+    assert_eq!(key(b"\x1b[3~"), name(b"delete"));
+    assert_eq!(key(b"\x1b[15;2~"), name(b"shift-f5"));
+    assert_eq!(key(b"\x1b[24~"), name(b"f12"));
+    assert_eq!(key(b"\x1b[Z"), name(b"shift-tab"));
+    assert_eq!(key(b"\x1b[27;5;113~"), name(b"ctrl-q"));
+    // kitty
+    assert_eq!(key(b"\x1b[27u"), name(b"esc"));
+    assert_eq!(key(b"\x1b[99;5u"), name(b"ctrl-c"));
+    assert_eq!(key(b"\x1b[97;2u"), name(b"A"));
+    assert_eq!(key(b"\x1b[1;3:1A"), name(b"alt-up"));
+    assert_eq!(key(b"\x1b[57376u"), name(b"f13"));
+    assert_eq!(key(b"\x1b[1;1:3A"), None);
+    // replies and broken input
+    assert_eq!(key(b"\x1b[?2026;2$y"), None);
+    assert_eq!(key(b"\xff"), None);
+    let (keys, count) = keys(&mut Decoder::new(), b"\x1b[1;\x1b[Aa\xe9b");
+    assert_eq!(count, 5);
+    assert_eq!(keys[1..5], [name(b"up"), name(b"a"), None, name(b"b")]);
+}
+
+#[test]
+fn test_split() {
+    let mut decoder = Decoder::new();
+    assert_eq!(
+        keys(&mut decoder, b"a\x1b[1;"),
+        (keys(&mut Decoder::new(), b"a").0, 1)
+    );
+    assert!(decoder.is_waiting());
+    assert_eq!(keys(&mut decoder, b"2D").0[0], name(b"shift-left"));
+    assert_eq!(keys(&mut decoder, b"\xe2\x82").1, 0);
+    assert_eq!(keys(&mut decoder, b"\xac").0[0], name("€".as_bytes()));
+    assert!(!decoder.is_waiting());
+
+    // until flushed, a lone ESC may start a sequence
+    assert_eq!(keys(&mut decoder, b"\x1b").1, 0);
+    decoder.flush();
+    assert_eq!(keys(&mut decoder, b"").0[0], name(b"esc"));
+    assert_eq!(keys(&mut decoder, b"\x1b[").1, 0);
+    decoder.flush();
+    assert_eq!(keys(&mut decoder, b"").0[0], name(b"alt-["));
+    // what is cut short is dropped, and flushing ends with it
+    assert_eq!(keys(&mut decoder, b"\x1b[12").1, 0);
+    decoder.flush();
+    assert_eq!(keys(&mut decoder, b""), ([None; 8], 1));
+    assert_eq!(keys(&mut decoder, b"\x1b").1, 0);
+    assert!(decoder.is_waiting());
+
+    fn name(name: &[u8]) -> Option<Key> {
+        Key::parse(name)
+    }
+}
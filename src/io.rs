@@ -54,9 +54,48 @@ impl FdReader {
         Self(STDIN)
     }
 
+    pub fn open(path: &[u8]) -> Result<Self> {
+        const PATH_MAX: usize = nc::PATH_MAX as _;
+        if path.len() >= PATH_MAX {
+            return Err(nc::ENAMETOOLONG);
+        }
+        let mut buf = core::mem::MaybeUninit::<[u8; PATH_MAX]>::uninit();
+        let buf = unsafe { buf.assume_init_mut() };
+        unsafe {
+            crate::utils::copy_nonoverlapping(path.as_ptr(), buf.as_mut_ptr(), path.len());
+            *buf.get_unchecked_mut(path.len()) = 0;
+            nc::syscalls::syscall4(
+                nc::SYS_OPENAT,
+                nc::AT_FDCWD as _,
+                buf.as_ptr() as _,
+                (nc::O_RDONLY | nc::O_CLOEXEC) as _,
+                0,
+            )
+        }
+        .map(|fd| Self(fd as _))
+    }
+
     pub fn read(self, buf: &mut [u8]) -> Result<usize> {
         unsafe { nc::read(self.0, buf) }.map(|x| x as _)
     }
+
+    /// Reads until end of file. Fails with `EFBIG` if `buf` fills up first.
+    pub fn read_to_end(self, buf: &mut [u8]) -> Result<usize> {
+        let mut len = 0;
+        loop {
+            if len == buf.len() {
+                break Err(nc::EFBIG);
+            }
+            match self.read(unsafe { buf.get_unchecked_mut(len..) })? {
+                0 => break Ok(len),
+                n => len += n,
+            }
+        }
+    }
+
+    pub fn close(self) -> Result<()> {
+        unsafe { nc::close(self.0) }
+    }
 }
 
 impl Write for FdWriter {
@@ -147,6 +186,12 @@ impl<Buffer: AsMut<[u8]>, Write: self::Write> self::Write for BufWriter<Buffer,
     }
 }
 
+impl<Buffer: AsMut<[u8]>, Write: self::Write> fmt::Write for BufWriter<Buffer, Write> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes()).map(|_| ()).map_err(|_| fmt::Error)
+    }
+}
+
 pub struct ArrayWriter<'a, const N: usize> {
     buf: &'a mut [u8; N],
     pub len: usize,
@@ -198,9 +243,3 @@ fn test_copy() {
     unsafe { crate::utils::copy_nonoverlapping(src.as_ptr(), dst.as_mut_ptr(), src.len()) };
     assert_eq!(dst[..src.len()], src[..])
 }
-
-//impl<Buffer: AsMut<[u8]>, Write: self::Write> fmt::Write for BufWriter<Buffer, Write> {
-//    fn write_str(&mut self, s: &str) -> fmt::Result {
-//        self.write(s.as_bytes()).map(|_| ()).map_err(|_| fmt::Error)
-//    }
-//}
@@ -0,0 +1,259 @@
+/// Modifier bits, as xterm encodes them less one in `CSI 1 ; mods X`.
+pub const SHIFT: u8 = 1;
+pub const ALT: u8 = 2;
+pub const CTRL: u8 = 4;
+
+pub const MAX_BINDINGS: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Code {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    /// Function keys from `F(1)` on.
+    F(u8),
+}
+
+/// A key with the modifiers held. Letters typed with shift are uppercase
+/// characters rather than carrying `SHIFT`, and those typed with ctrl are
+/// lowercase.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Key {
+    pub code: Code,
+    pub mods: u8,
+}
+
+const NAMES: [(&[u8], Code); 15] = [
+    (b"space", Code::Char(' ')),
+    (b"enter", Code::Enter),
+    (b"tab", Code::Tab),
+    (b"backspace", Code::Backspace),
+    (b"esc", Code::Esc),
+    (b"up", Code::Up),
+    (b"down", Code::Down),
+    (b"left", Code::Left),
+    (b"right", Code::Right),
+    (b"home", Code::Home),
+    (b"end", Code::End),
+    (b"pageup", Code::PageUp),
+    (b"pagedown", Code::PageDown),
+    (b"insert", Code::Insert),
+    (b"delete", Code::Delete),
+];
+
+impl Key {
+    pub const fn new(code: Code, mods: u8) -> Self {
+        Self { code, mods }.normalize()
+    }
+
+    const fn normalize(mut self) -> Self {
+        if let Code::Char(c) = self.code {
+            if self.mods & CTRL != 0 {
+                self.code = Code::Char(c.to_ascii_lowercase());
+            } else if self.mods & SHIFT != 0 && c.is_ascii_lowercase() {
+                self.code = Code::Char(c.to_ascii_uppercase());
+                self.mods &= !SHIFT;
+            }
+        }
+        self
+    }
+
+    /// The key sent as the single byte `b`, control bytes being ctrl and a
+    /// letter.
+    pub const fn from_byte(b: u8) -> Option<Self> {
+        let code = match b {
+            b'\r' | b'\n' => Code::Enter,
+            b'\t' => Code::Tab,
+            0x7f | 0x08 => Code::Backspace,
+            0x1b => Code::Esc,
+            0 => return Some(Self::new(Code::Char(' '), CTRL)),
+            1..=0x1a => return Some(Self::new(Code::Char((b'a' + b - 1) as char), CTRL)),
+            0x20..=0x7e => Code::Char(b as char),
+            _ => return None,
+        };
+        Some(Self::new(code, 0))
+    }
+
+    /// Parses names like `q`, `space`, `f5` or `ctrl-alt-up`.
+    pub fn parse(name: &[u8]) -> Option<Self> {
+        let mut mods = 0;
+        let mut rest = name;
+        'prefixes: loop {
+            for (prefix, bit) in [(&b"ctrl-"[..], CTRL), (b"alt-", ALT), (b"shift-", SHIFT)] {
+                if rest.len() > prefix.len() && rest.starts_with(prefix) {
+                    mods |= bit;
+                    rest = &rest[prefix.len()..];
+                    continue 'prefixes;
+                }
+            }
+            break;
+        }
+        let code = match NAMES.iter().find(|(name, _)| *name == rest) {
+            Some(&(_, code)) => code,
+            None => match rest {
+                [b'f', n @ b'1'..=b'9'] => Code::F(n - b'0'),
+                [b'f', b'1', n @ b'0'..=b'9'] => Code::F(10 + n - b'0'),
+                [b'f', b'2', n @ b'0'..=b'4'] => Code::F(20 + n - b'0'),
+                _ => {
+                    let mut chars = core::str::from_utf8(rest).ok()?.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Code::Char(c),
+                        _ => return None,
+                    }
+                }
+            },
+        };
+        Some(Self::new(code, mods))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Quit,
+    /// Starts or pauses the stopwatch or timer, or dismisses an expired timer.
+    StartStop,
+    Lap,
+    Reset,
+    AddMinute,
+    SubtractMinute,
+    ToggleSeconds,
+    /// From the clock to the stopwatch to the timer, if there is one.
+    NextMode,
+    CycleTheme,
+    /// For Ctrl-Z, which the terminal sends as a key rather than a signal when
+    /// keys are reported the kitty way.
+    Suspend,
+}
+
+const ACTIONS: [(&[u8], Action); 10] = [
+    (b"quit", Action::Quit),
+    (b"start-stop", Action::StartStop),
+    (b"lap", Action::Lap),
+    (b"reset", Action::Reset),
+    (b"add-minute", Action::AddMinute),
+    (b"subtract-minute", Action::SubtractMinute),
+    (b"toggle-seconds", Action::ToggleSeconds),
+    (b"next-mode", Action::NextMode),
+    (b"cycle-theme", Action::CycleTheme),
+    (b"suspend", Action::Suspend),
+];
+
+impl Action {
+    pub fn parse(name: &[u8]) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(action, _)| *action == name)
+            .map(|&(_, action)| action)
+    }
+}
+
+const DEFAULTS: [(Key, Action); 14] = [
+    (Key::new(Code::Char('q'), 0), Action::Quit),
+    (Key::new(Code::Esc, 0), Action::Quit),
+    (Key::new(Code::Char('c'), CTRL), Action::Quit),
+    (Key::new(Code::Char(' '), 0), Action::StartStop),
+    (Key::new(Code::Char('l'), 0), Action::Lap),
+    (Key::new(Code::Enter, 0), Action::Lap),
+    (Key::new(Code::Char('r'), 0), Action::Reset),
+    (Key::new(Code::Char('+'), 0), Action::AddMinute),
+    (Key::new(Code::Char('='), 0), Action::AddMinute),
+    (Key::new(Code::Char('-'), 0), Action::SubtractMinute),
+    (Key::new(Code::Char('s'), 0), Action::ToggleSeconds),
+    (Key::new(Code::Char('m'), 0), Action::NextMode),
+    (Key::new(Code::Char('t'), 0), Action::CycleTheme),
+    (Key::new(Code::Char('z'), CTRL), Action::Suspend),
+];
+
+/// Keys bound to actions, each to one at most.
+#[derive(Clone, Copy)]
+pub struct Keymap {
+    bindings: [(Key, Action); MAX_BINDINGS],
+    len: usize,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: [DEFAULTS[0]; MAX_BINDINGS],
+            len: DEFAULTS.len(),
+        };
+        keymap.bindings[..DEFAULTS.len()].copy_from_slice(&DEFAULTS);
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Binds `key` in place of what it was bound to; `false` when full.
+    pub fn bind(&mut self, key: Key, action: Action) -> bool {
+        match self.bindings[..self.len]
+            .iter_mut()
+            .find(|(k, _)| *k == key)
+        {
+            Some(binding) => binding.1 = action,
+            None if self.len == MAX_BINDINGS => return false,
+            None => {
+                self.bindings[self.len] = (key, action);
+                self.len += 1;
+            }
+        }
+        true
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings[..self.len]
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|&(_, action)| action)
+    }
+}
+
+#[test]
+fn test_parse() {
+    let key = |name: &[u8]| Key::parse(name).unwrap();
+    assert_eq!(key(b"q"), Key::new(Code::Char('q'), 0));
+    assert_eq!(key(b"space"), Key::new(Code::Char(' '), 0));
+    assert_eq!(key(b"ctrl-alt-up"), Key::new(Code::Up, CTRL | ALT));
+    assert_eq!(key(b"shift-f12"), Key::new(Code::F(12), SHIFT));
+    assert_eq!(key(b"f24"), Key::new(Code::F(24), 0));
+    assert_eq!(key(b"-"), Key::new(Code::Char('-'), 0));
+    assert_eq!(key(b"ctrl--"), Key::new(Code::Char('-'), CTRL));
+    assert_eq!(key("é".as_bytes()), Key::new(Code::Char('é'), 0));
+    // as terminals send them
+    assert_eq!(key(b"shift-a"), key(b"A"));
+    assert_eq!(key(b"ctrl-C"), Key::from_byte(3).unwrap());
+    assert_eq!(Key::from_byte(b'\n'), Some(key(b"enter")));
+    assert_eq!(Key::parse(b"f25"), None);
+    assert_eq!(Key::parse(b"ctrl-"), None);
+    assert_eq!(Key::parse(b"qq"), None);
+    assert_eq!(Key::parse(b""), None);
+}
+
+#[test]
+fn test_keymap() {
+    let key = |name: &[u8]| Key::parse(name).unwrap();
+    let mut keymap = Keymap::default();
+    assert_eq!(keymap.action(key(b"esc")), Some(Action::Quit));
+    assert_eq!(keymap.action(key(b"x")), None);
+    assert!(keymap.bind(key(b"q"), Action::Lap));
+    assert!(keymap.bind(key(b"ctrl-q"), Action::Quit));
+    assert_eq!(keymap.action(key(b"q")), Some(Action::Lap));
+    assert_eq!(keymap.action(key(b"ctrl-q")), Some(Action::Quit));
+    for n in 1..=24 {
+        keymap.bind(Key::new(Code::F(n), 0), Action::Reset);
+    }
+    assert!(!keymap.bind(key(b"alt-x"), Action::Reset));
+    assert_eq!(Action::parse(b"cycle-theme"), Some(Action::CycleTheme));
+    assert_eq!(Action::parse(b"explode"), None);
+}
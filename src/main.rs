@@ -4,19 +4,45 @@
 #![feature(concat_bytes, const_trait_impl, naked_functions)]
 
 use core::{
-    alloc::GlobalAlloc, arch::naked_asm, cell::Cell, mem::MaybeUninit, panic::PanicInfo,
+    alloc::GlobalAlloc,
+    arch::naked_asm,
+    cell::{Cell, RefCell},
+    fmt::Write as _,
+    mem::MaybeUninit,
+    panic::PanicInfo,
     ptr::null_mut,
 };
 
-use draw::draw_time;
-use io::{ArrayWriter, BufWriter, FdWriter, Write as _};
-use io_uring::IoUring;
+use draw::{
+    Element, Font, GAP_WIDTH, Hours, Palette, Text,
+    color::{Color, Literal},
+    format_stopwatch, format_time, format_timer,
+    frame::{self, Frame},
+};
+use event::{EventLoop, IORING_TIMEOUT_REALTIME};
+use fmt::{Bytes, Strftime};
+use input::Decoder;
+use io::{BufWriter, FdWriter, Write as _};
+use keymap::Action;
+use parse::{Command, MAX_ZONES, Mode, Options};
+use stopwatch::{Centis, Stopwatch};
+use time::{Itimerspec, Timespec};
+use timer::Timer;
 
+pub mod config;
 pub mod draw;
+pub mod env;
+pub mod event;
 pub mod fmt;
+pub mod input;
 pub mod io;
 pub mod io_uring;
+pub mod keymap;
 pub mod parse;
+pub mod stopwatch;
+pub mod time;
+pub mod timer;
+pub mod tz;
 
 #[macro_export]
 macro_rules! print {
@@ -67,6 +93,60 @@ macro_rules! cursor_position {
     };
 }
 
+#[macro_export]
+macro_rules! clear_screen {
+    () => {
+        b"[2J"
+    };
+}
+
+#[macro_export]
+macro_rules! bell {
+    () => {
+        b"\x07"
+    };
+}
+
+/// Holds off showing output until `end_synchronized!`, on terminals with
+/// DEC mode 2026.
+#[macro_export]
+macro_rules! begin_synchronized {
+    () => {
+        b"[?2026h"
+    };
+}
+
+#[macro_export]
+macro_rules! end_synchronized {
+    () => {
+        b"[?2026l"
+    };
+}
+
+/// Asks whether DEC mode 2026 is supported, see `parse::mode_report`.
+#[macro_export]
+macro_rules! query_synchronized {
+    () => {
+        b"[?2026$p"
+    };
+}
+
+/// Has keys reported unambiguously as `CSI u`, on terminals with the kitty
+/// keyboard protocol, until `pop_key_flags!`.
+#[macro_export]
+macro_rules! push_key_flags {
+    () => {
+        b"[>1u"
+    };
+}
+
+#[macro_export]
+macro_rules! pop_key_flags {
+    () => {
+        b"[<u"
+    };
+}
+
 #[macro_export]
 macro_rules! buffer_size {
     () => {
@@ -127,9 +207,29 @@ macro_rules! fg_color {
     };
 }
 
+/// Turns off echo and line buffering, then switches to the alternate screen.
+fn on_enter() -> io::Result<()> {
+    #[allow(static_mut_refs)]
+    unsafe {
+        let mut termios = TERMIOS.assume_init_ref().clone();
+        termios.c_lflag &= !(nc::ECHO | nc::ICANON);
+        nc::ioctl(io::STDIN, nc::TCSETS, &raw const termios as _)?;
+    }
+    // the alternate screen keeps its own flags
+    FdWriter::stdout().write_all(concat_bytes!(
+        set_buffer!(),
+        hide_cursor!(),
+        push_key_flags!()
+    ))
+}
+
 #[inline(always)]
 fn on_exit() -> io::Result<()> {
-    FdWriter::stdout().write_all(concat_bytes!(restore_buffer!(), show_cursor!()))?;
+    FdWriter::stdout().write_all(concat_bytes!(
+        pop_key_flags!(),
+        restore_buffer!(),
+        show_cursor!()
+    ))?;
 
     #[allow(static_mut_refs)]
     unsafe {
@@ -139,209 +239,914 @@ fn on_exit() -> io::Result<()> {
     Ok(())
 }
 
-#[cfg(target_arch = "x86_64")]
-#[naked]
-extern "C" fn restorer() {
-    unsafe { naked_asm!("mov rax, 0xf", "syscall",) }
-}
-
-struct MarginBuf {
-    buf: [u8; 32],
-    len: u8,
-}
-
-impl MarginBuf {
-    fn slice(&self) -> &[u8] {
-        unsafe { self.buf.get_unchecked(..self.len as _) }
-    }
-
-    fn cursor_move(&mut self, n: usize, direction: Direction) -> io::Result<()> {
-        let mut writer = ArrayWriter::new(&mut self.buf);
-        cursor_move(&mut writer, n as _, direction)?;
-        self.len = writer.len as _;
-        Ok(())
-    }
-}
-
 fn resize() -> io::Result<()> {
-    let winsz = MaybeUninit::<nc::winsize_t>::uninit();
+    let mut winsz = MaybeUninit::<nc::winsize_t>::uninit();
     #[allow(static_mut_refs)]
     unsafe {
-        nc::ioctl(io::STDIN, nc::TIOCGWINSZ, winsz.as_ptr() as _)
+        nc::ioctl(io::STDIN, nc::TIOCGWINSZ, winsz.as_mut_ptr() as _)
             .unwrap_or_else(|e| utils::exit(e as _));
-        let nc::winsize_t { ws_row, ws_col, .. } = winsz.assume_init_ref();
+        let &mut nc::winsize_t { ws_row, ws_col, .. } = WINSIZE.write(winsz.assume_init());
+
+        let ws_col = (ws_col as usize).min(frame::MAX_COLUMNS);
+        let ws_row = (ws_row as usize).min(frame::MAX_LINES);
 
-        MARGIN_LEFT
-            .assume_init_mut()
-            .cursor_move(((ws_col - 38) / 2) as _, Direction::Right)?;
-        MARGIN_TOP
-            .assume_init_mut()
-            .cursor_move(((ws_row - 5) / 2) as _, Direction::Down)?;
+        LAYOUT.fit(ws_col, ws_row);
     };
     Ok(())
 }
 
-fn set_signal_handler() {
-    extern "C" fn terminate(_: i32) {
-        _ = on_exit();
-        utils::exit(0);
-    }
+static mut TERMIOS: MaybeUninit<nc::termios_t> = MaybeUninit::uninit();
+static mut WINSIZE: MaybeUninit<nc::winsize_t> = MaybeUninit::uninit();
+static mut FRAMES: MaybeUninit<[Frame; 2]> = MaybeUninit::uninit();
+/// Two, so that the settings read from one last until those in the other parse.
+static mut CONFIG: MaybeUninit<[[u8; config::CONFIG_MAX]; 2]> = MaybeUninit::uninit();
+static mut TZBUFS: MaybeUninit<[[u8; tz::TZIF_MAX]; MAX_ZONES]> = MaybeUninit::uninit();
+static mut LAYOUT: Layout = Layout {
+    blocks: 1,
+    label_lines: 0,
+    meridiem: false,
+    largest: Font::Large,
+    seconds: true,
+    font: Some(Font::Large),
+    chars: 8,
+    columns: 1,
+    scale: 1,
+    top: 0,
+    left: 0,
+};
 
-    unsafe {
-        let sa = nc::sigaction_t {
-            sa_handler: terminate as _,
-            sa_flags: nc::SA_RESTORER,
-            sa_restorer: None,
-            ..Default::default()
+/// Fonts and how many characters of `HH:MM:SS` to show, tried in turn until
+/// one fits: seconds are dropped first, then the font shrinks.
+const FALLBACKS: [(Font, usize); 4] = [
+    (Font::Large, 8),
+    (Font::Large, 5),
+    (Font::Compact, 5),
+    (Font::Text, 5),
+];
+
+const TOO_SMALL: &[u8] = b"terminal too small";
+
+/// Palettes that `cycle-theme` goes through after the configured one.
+const THEMES: [Palette; 3] = [
+    Palette::uniform(Color::Bright(Literal::Green), Color::Clear),
+    Palette::uniform(Color::Ansi(214), Color::Clear),
+    Palette::uniform(Color::Normal(Literal::Black), Color::Bright(Literal::White)),
+];
+
+/// Grid of clock blocks, one line apart, filled row by row.
+#[derive(Clone, Copy)]
+struct Layout {
+    blocks: usize,
+    /// Lines under each block for its label.
+    label_lines: usize,
+    /// Whether times are followed by `AM` or `PM`.
+    meridiem: bool,
+    /// Where in `FALLBACKS` to start.
+    largest: Font,
+    /// Whether to try the formats with seconds, turned off by `toggle-seconds`.
+    seconds: bool,
+    /// The rest is set by `resize`, `font` being `None` when nothing fits.
+    font: Option<Font>,
+    chars: usize,
+    /// Blocks per row.
+    columns: usize,
+    scale: usize,
+    /// Where the grid starts, centering it.
+    top: usize,
+    left: usize,
+}
+
+impl Layout {
+    /// Picks the format and scale for a terminal of `ws_col` by `ws_row`.
+    fn fit(&mut self, ws_col: usize, ws_row: usize) {
+        // as many columns as fit, balanced over the rows needed
+        let Layout {
+            blocks,
+            label_lines,
+            meridiem,
+            largest,
+            seconds,
+            ..
+        } = *self;
+        let grid = |font: Font, chars: usize, scale: usize| {
+            let template = Text::new(&[&b"00:00:00"[..chars], if meridiem { b" AM" } else { b"" }]);
+            let block = font.width(&template) * scale;
+            let gap = GAP_WIDTH * scale;
+            let fit = (ws_col + gap) / (block + gap);
+            let rows = blocks.div_ceil(fit.clamp(1, blocks));
+            let columns = blocks.div_ceil(rows);
+            let lines = rows * (font.height() * scale + label_lines + 1) - 1;
+            (columns, columns * (block + gap) - gap, lines)
         };
-        _ = nc::rt_sigaction(nc::SIGINT, Some(&sa), None);
-        _ = nc::rt_sigaction(nc::SIGTERM, Some(&sa), None);
+        let fits = |(_, width, lines)| width <= ws_col && lines <= ws_row;
 
-        let sa = nc::sigaction_t {
-            sa_handler: resize as _,
-            sa_flags: nc::SA_RESTORER | nc::SA_RESTART,
-            sa_restorer: Some(restorer),
-            ..Default::default()
+        // the first format that fits, at the largest scale that still does
+        let format = FALLBACKS
+            .into_iter()
+            .skip_while(|&(font, _)| font != largest)
+            .filter(|&(_, chars)| seconds || chars < 8)
+            .find(|&(font, chars)| fits(grid(font, chars, 1)));
+        self.font = format.map(|(font, _)| font);
+        let (width, height) = match format {
+            Some((font, chars)) => {
+                let scale = (2..)
+                    .take_while(|&scale| font != Font::Text && fits(grid(font, chars, scale)))
+                    .last()
+                    .unwrap_or(1);
+                let (columns, width, lines) = grid(font, chars, scale);
+                self.chars = chars;
+                self.columns = columns;
+                self.scale = scale;
+                (width, lines)
+            }
+            None => (TOO_SMALL.len(), 1),
         };
-        _ = nc::rt_sigaction(nc::SIGWINCH, Some(&sa), None);
+        self.top = ws_row.saturating_sub(height) / 2;
+        self.left = ws_col.saturating_sub(width) / 2;
     }
 }
 
-static mut TERMIOS: MaybeUninit<nc::termios_t> = MaybeUninit::uninit();
-static mut MARGIN_LEFT: MaybeUninit<MarginBuf> = MaybeUninit::uninit();
-static mut MARGIN_TOP: MaybeUninit<MarginBuf> = MaybeUninit::uninit();
-
-fn margin_left() -> &'static [u8] {
+fn config_buffer(i: usize) -> &'static mut [u8] {
     #[allow(static_mut_refs)]
-    unsafe { MARGIN_LEFT.assume_init_ref() }.slice()
+    unsafe {
+        &mut CONFIG.assume_init_mut()[i]
+    }
 }
 
-fn margin_top() -> &'static [u8] {
-    #[allow(static_mut_refs)]
-    unsafe { MARGIN_TOP.assume_init_ref() }.slice()
+/// `path:line: message`, or `path: message` when it cannot be read.
+fn config_error(
+    f: &mut impl core::fmt::Write,
+    path: &[u8],
+    e: &config::Error,
+) -> core::fmt::Result {
+    match e {
+        config::Error::Read(_) => write!(f, "{}: {e}", Bytes(path)),
+        config::Error::Line(_) => write!(f, "{}:{e}", Bytes(path)),
+    }
 }
 
-#[repr(u8)]
-#[allow(unused)]
-enum Direction {
-    Up = b'A',
-    Down = b'B',
-    Right = b'C',
-    Left = b'D',
+fn layout() -> Layout {
+    unsafe { LAYOUT }
 }
 
-fn cursor_move(writer: &mut impl io::Write, n: u64, direction: Direction) -> io::Result<()> {
-    writer.write_all(b"[")?;
-    writer.write_u64(n)?;
-    writer.write_all(&[direction as _][..])?;
+fn winsize() -> &'static nc::winsize_t {
+    #[allow(static_mut_refs)]
+    unsafe {
+        WINSIZE.assume_init_ref()
+    }
+}
+
+/// Lists laps beneath the digits from `line` on, as many as fit under the
+/// centered block.
+fn draw_laps<W: io::Write>(
+    ctx: &mut draw::Context<W>,
+    line: usize,
+    stopwatch: &Stopwatch,
+) -> io::Result<()> {
+    let Layout {
+        font, scale, left, ..
+    } = layout();
+    let height = (font.map_or(0, Font::height) * scale) as u16;
+    let rows = (winsize().ws_row.saturating_sub(height) / 2).saturating_sub(1);
+    for (i, lap) in stopwatch.laps().take(rows as _).enumerate() {
+        let mut buf = fmt::Buf::<64>::new();
+        buf.push(b"Lap ");
+        buf.push_number(lap.number as _, 2, b' ');
+        buf.push(b"   ");
+        Centis(lap.split).write_to(&mut buf);
+        buf.push(b"   ");
+        Centis(lap.total).write_to(&mut buf);
+        ctx.text(line + 1 + i, left + 5, buf.as_bytes(), Element::Labels);
+    }
     Ok(())
 }
 
-fn main() -> io::Result<()> {
+/// Returns the exit status. Settings follow edits of the config file at
+/// `config`, first read into `CONFIG` buffer 0.
+fn main(options: Options, config: Option<&[u8]>) -> io::Result<u8> {
+    let options = Cell::new(options);
+    let buffer = Cell::new(0);
     let mut buf = MaybeUninit::<[u8; 1024]>::uninit();
     let buf = unsafe { buf.assume_init_mut() };
-    let mut ctx = draw::Context::new(BufWriter::new(FdWriter::stdout(), buf));
+    #[allow(static_mut_refs)]
+    let frames = unsafe { FRAMES.assume_init_mut() };
+    let mut ctx = draw::Context::new(BufWriter::new(FdWriter::stdout(), buf), frames);
+    ctx.palette = options.get().palette;
 
-    let get_time = || -> io::Result<isize> {
-        let mut time = MaybeUninit::uninit();
-        unsafe {
-            nc::time(time.assume_init_mut())?;
-            Ok(time.assume_init())
-        }
+    let get_time = || -> io::Result<i64> {
+        // not `time`, whose coarse clock may not have reached a second its timeout fired on
+        Ok(time::clock_gettime(nc::CLOCK_REALTIME)?.tv_sec)
     };
 
     let seconds = Cell::new(get_time()?);
 
+    let get_centis = || -> io::Result<u64> {
+        let time = time::clock_gettime(nc::CLOCK_MONOTONIC)?;
+        Ok(time.tv_sec as u64 * 100 + time.tv_nsec as u64 / 10_000_000)
+    };
+
+    let centis = Cell::new(get_centis()?);
+    let stopwatch = RefCell::new(Stopwatch::new());
+    let timer = RefCell::new(match options.get().mode {
+        Mode::Timer(seconds) => {
+            Timer::new(seconds.checked_mul(100).ok_or(nc::EINVAL)?, centis.get())
+        }
+        _ => Timer::new(0, 0),
+    });
+    // where `next-mode` goes after the stopwatch, a timer only if one was given
+    let after_stopwatch = match options.get().mode {
+        Mode::Timer(seconds) => Mode::Timer(seconds),
+        _ => Mode::Clock,
+    };
+    // what the timer shows: remaining seconds and the flash phase, twice a second
+    let timer_frame_at = |now| {
+        let timer = timer.borrow();
+        (
+            timer.remaining_seconds(now),
+            timer.alert(now).map(|since| since / 50),
+        )
+    };
+    let timer_frame = Cell::new(timer_frame_at(centis.get()));
+    let synchronized = Cell::new(false);
+    // set when the next frame is to be written whole
+    let repaint = Cell::new(false);
+    // 0 for the configured palette, then one of `THEMES`
+    let theme = Cell::new(0);
+
+    let zones = RefCell::new(core::array::from_fn(|_| tz::Zone::Utc));
+    let offsets = Cell::new([tz::Offset::UTC; MAX_ZONES]);
+    let hours = Cell::new(Hours::H24);
+    // what follows from the options, again for each edit of the config file
+    let settle = |zones_changed: bool| {
+        let options = options.get();
+        let count = options.zones().len().max(1);
+        if zones_changed {
+            // one clock per `--zone`, or the local time; unused slots stay UTC
+            zones.replace(core::array::from_fn(|_| tz::Zone::Utc));
+            #[allow(static_mut_refs)]
+            let mut tzbufs = unsafe { TZBUFS.assume_init_mut() }.iter_mut();
+            zones.replace(core::array::from_fn(|i| match tzbufs.next() {
+                Some(buf) if i < count => {
+                    let name = options.zones().get(i).copied();
+                    tz::resolve(name.or_else(|| env::var(b"TZ")), buf)
+                }
+                _ => tz::Zone::Utc,
+            }));
+            offsets.set(
+                zones
+                    .borrow()
+                    .each_ref()
+                    .map(|zone| zone.lookup(seconds.get())),
+            );
+        }
+        hours.set(options.hours.unwrap_or_else(|| {
+            match env::locale(b"LC_TIME").is_some_and(fmt::twelve_hour_locale) {
+                true => Hours::H12,
+                false => Hours::H24,
+            }
+        }));
+        // the stopwatch and the timer are a single block without labels
+        let clock = matches!(options.mode, Mode::Clock);
+        unsafe {
+            LAYOUT.largest = options.font;
+            LAYOUT.meridiem = clock && hours.get() == Hours::H12;
+            LAYOUT.blocks = if clock { count } else { 1 };
+            LAYOUT.label_lines = match clock {
+                true => options.date.is_some() as usize + !options.labels().is_empty() as usize,
+                false => 0,
+            };
+        }
+    };
+    settle(true);
+    // why the last edit of the config file was not taken, shown on the bottom line
+    let config_message = RefCell::new(fmt::Buf::<128>::new());
+
     let mut redraw = || -> io::Result<()> {
-        ctx.writer.write_all(concat_bytes!(
-            restore_buffer!(),
-            set_buffer!(),
-            cursor_position!(),
-            fg_color!(br_blue),
-        ))?;
-        ctx.writer.write_all(margin_top())?;
-        let content = draw_time(seconds.get() + 8 * 3600);
-        ctx.draw(Some(margin_left()), || content)?;
+        let Layout {
+            font,
+            chars,
+            columns,
+            scale,
+            top,
+            left,
+            ..
+        } = layout();
+        let options = options.get();
+        let count = options.zones().len().max(1);
+        // the expired timer flashes by alternating palettes
+        ctx.palette = match (options.mode, timer_frame.get()) {
+            (Mode::Timer(_), (_, Some(flash))) if flash % 2 == 0 => {
+                Palette::uniform(Color::Bright(Literal::White), Color::Normal(Literal::Red))
+            }
+            (Mode::Timer(_), (_, Some(_))) => {
+                Palette::uniform(Color::Bright(Literal::Red), options.palette.background)
+            }
+            _ => match theme.get() {
+                0 => options.palette,
+                i => THEMES[i - 1],
+            },
+        };
+        let &nc::winsize_t { ws_col, ws_row, .. } = winsize();
+        ctx.synchronized = synchronized.get();
+        if repaint.replace(false) {
+            ctx.invalidate();
+        }
+        ctx.clear(ws_col as _, ws_row as _);
+        let message = config_message.borrow();
+        ctx.text(
+            ws_row.saturating_sub(1) as _,
+            0,
+            message.as_bytes(),
+            Element::Labels,
+        );
+        let Some(font) = font else {
+            ctx.text(top, left, TOO_SMALL, Element::Labels);
+            ctx.present()?;
+            ctx.writer.flush()?;
+            return Ok(());
+        };
+        ctx.font = font;
+        ctx.scale = scale;
+        match options.mode {
+            Mode::Clock => {
+                let offsets = offsets.get();
+                let times: [_; MAX_ZONES] = core::array::from_fn(|i| {
+                    let seconds = seconds.get() + offsets[i].utc_offset as i64;
+                    let (time, meridiem) =
+                        format_time(seconds, hours.get(), !options.no_leading_zero);
+                    Text::new(&[&time[..chars], meridiem])
+                });
+                let texts = times.each_ref().map(|time| &**time);
+                let dates: [_; MAX_ZONES] = core::array::from_fn(|i| {
+                    let mut buf = fmt::Buf::<64>::new();
+                    if let Some(format) = options.date {
+                        let utc_offset = offsets[i].utc_offset;
+                        let time = seconds.get();
+                        Strftime {
+                            format,
+                            time,
+                            utc_offset,
+                        }
+                        .write_to(&mut buf);
+                    }
+                    buf
+                });
+                let dates = match options.date {
+                    Some(_) => &dates.each_ref().map(fmt::Buf::as_bytes)[..count],
+                    None => &[],
+                };
+                let mut line = top;
+                for (row, texts) in texts[..count].chunks(columns).enumerate() {
+                    let start = row * columns;
+                    let captions = [dates, options.labels()]
+                        .map(|line| line.get(start..start + texts.len()).unwrap_or_default());
+                    // a blank line between rows
+                    line = ctx.draw(line, left, texts, &captions) + 1;
+                }
+            }
+            Mode::Stopwatch => {
+                let stopwatch = stopwatch.borrow();
+                let text = format_stopwatch(stopwatch.elapsed(centis.get()));
+                let line = ctx.draw(top, left, &[&text[..chars]], &[]);
+                draw_laps(&mut ctx, line, &stopwatch)?;
+            }
+            Mode::Timer(_) => {
+                let text = format_timer(timer_frame.get().0);
+                ctx.draw(top, left, &[&text[..chars]], &[]);
+            }
+        }
+        ctx.present()?;
         ctx.writer.flush()?;
         Ok(())
     };
 
+    // signals are only read from a signalfd, as completions like any other
+    let mut signals = nc::sigset_t::default();
+    for signal in [
+        nc::SIGINT,
+        nc::SIGTERM,
+        nc::SIGHUP,
+        nc::SIGQUIT,
+        nc::SIGTSTP,
+        nc::SIGCONT,
+        nc::SIGWINCH,
+    ] {
+        signals.sig[0] |= 1 << (signal - 1);
+    }
+    let signal_fd = unsafe {
+        nc::rt_sigprocmask(nc::SIG_BLOCK, Some(&signals), None)?;
+        nc::signalfd4(-1, &signals, nc::O_CLOEXEC)?
+    };
+
     #[allow(static_mut_refs)]
     unsafe {
-        nc::ioctl(io::STDIN, nc::TCGETS, TERMIOS.as_ptr() as _)?;
-        let mut termios = TERMIOS.assume_init_ref().clone();
-        termios.c_lflag &= !(nc::ECHO | nc::ICANON);
-        nc::ioctl(io::STDIN, nc::TCSETS, &raw const termios as _)?;
+        nc::ioctl(io::STDIN, nc::TCGETS, TERMIOS.as_mut_ptr() as _)?;
     }
-
+    on_enter()?;
+    // a reply to the query turns on synchronized output
+    FdWriter::stdout().write_all(query_synchronized!())?;
     resize()?;
     redraw()?;
-    set_signal_handler();
-    FdWriter::stdout().write_all(hide_cursor!())?;
 
     #[repr(usize)]
     enum Token {
         Timeout = 1,
         Read,
+        ClockSet,
+        Signal,
+        Config,
+        Escape,
+        TimeoutRemove,
     }
-    let ring = IoUring::new(2)?;
+    let ring = EventLoop::new(8)?;
 
     let mut input_buf = MaybeUninit::<[u8; 32]>::uninit();
-    let mut sigset = nc::sigset_t::default();
-    sigset.sig[0] |= 1 << (nc::SIGWINCH) - 1;
     ring.prepare_read(
         io::STDIN as _,
         unsafe { input_buf.assume_init_mut() },
         Token::Read as _,
     );
-    let duration = nc::timespec_t {
-        tv_sec: 1,
-        tv_nsec: 0,
+    // a `signalfd_siginfo`, starting with the signal number
+    let mut siginfo = MaybeUninit::<[u8; 128]>::uninit();
+    ring.prepare_read(
+        signal_fd as _,
+        unsafe { siginfo.assume_init_mut() },
+        Token::Signal as _,
+    );
+    // one timeout for the mode shown, re-armed every tick; those left from a
+    // mode switched away from or a jump of the clock carry an older generation
+    // in their user data
+    const ABS_REALTIME: u32 = nc::IORING_TIMEOUT_ABS | IORING_TIMEOUT_REALTIME;
+    let generation = Cell::new(0u64);
+    // kernels before 5.15 refuse realtime timeouts
+    let realtime = Cell::new(true);
+    let arm = |timeout: &mut nc::kernel_timespec_t| -> io::Result<()> {
+        let (next, flags) = match options.get().mode {
+            // the clock wakes on each wall-clock second
+            Mode::Clock if realtime.get() => (
+                nc::kernel_timespec_t {
+                    tv_sec: seconds.get() + 1,
+                    tv_nsec: 0,
+                },
+                ABS_REALTIME,
+            ),
+            // or waits out the rest of it, if that is refused
+            Mode::Clock => {
+                let now = time::clock_gettime(nc::CLOCK_REALTIME)?;
+                (
+                    nc::kernel_timespec_t {
+                        tv_sec: 0,
+                        tv_nsec: 1_000_000_000 - now.tv_nsec,
+                    },
+                    0,
+                )
+            }
+            // hundredths of a second
+            Mode::Stopwatch => (
+                nc::kernel_timespec_t {
+                    tv_sec: 0,
+                    tv_nsec: 10_000_000,
+                },
+                0,
+            ),
+            // fine enough to flip seconds and flash without visible delay
+            Mode::Timer(_) => (
+                nc::kernel_timespec_t {
+                    tv_sec: 0,
+                    tv_nsec: 100_000_000,
+                },
+                0,
+            ),
+        };
+        // read by the kernel on the next `submit`
+        *timeout = next;
+        let user_data = Token::Timeout as u64 | generation.get() << 8;
+        ring.prepare_timeout(timeout, user_data, flags);
+        Ok(())
     };
-    ring.prepare_timeout(&duration, Token::Timeout as _, 1 << 6); // multishot
+    let mut timeout = nc::kernel_timespec_t::default();
+    arm(&mut timeout)?;
 
-    ring.submit(2)?;
+    // setting the wall clock cancels a read of this never-expiring timerfd
+    let clock_set = unsafe { nc::timerfd_create(nc::CLOCK_REALTIME, nc::TFD_CLOEXEC)? };
+    let arm_clock_set = || {
+        let never = Itimerspec {
+            it_value: Timespec {
+                tv_sec: i64::MAX,
+                tv_nsec: 0,
+            },
+            ..Default::default()
+        };
+        let flags = nc::TFD_TIMER_ABSTIME | nc::TFD_TIMER_CANCEL_ON_SET;
+        time::timerfd_settime(clock_set, flags, &never)
+    };
+    let mut expirations = MaybeUninit::<[u8; 8]>::uninit();
+    arm_clock_set()?;
+    ring.prepare_read(
+        clock_set as _,
+        unsafe { expirations.assume_init_mut() },
+        Token::ClockSet as _,
+    );
+
+    // edits of the config file, seen in its directory
+    let config_watch = config.and_then(|path| config::watch(path).ok());
+    let mut events = MaybeUninit::<[u8; 1024]>::uninit();
+    if let Some(fd) = config_watch {
+        ring.prepare_read(
+            fd as _,
+            unsafe { events.assume_init_mut() },
+            Token::Config as _,
+        );
+    }
+
+    // input cut short waits this long for the rest, then is taken as is, a
+    // lone ESC being the Esc key
+    const ESCAPE_TIMEOUT: Itimerspec = Itimerspec {
+        it_interval: Timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        },
+        it_value: Timespec {
+            tv_sec: 0,
+            tv_nsec: 50_000_000,
+        },
+    };
+    let mut decoder = Decoder::new();
+    let escape_timer = unsafe { nc::timerfd_create(nc::CLOCK_MONOTONIC, nc::TFD_CLOEXEC)? };
+    let mut escape_expirations = MaybeUninit::<[u8; 8]>::uninit();
+    ring.prepare_read(
+        escape_timer as _,
+        unsafe { escape_expirations.assume_init_mut() },
+        Token::Escape as _,
+    );
+
+    // a suspend moves BOOTTIME away from MONOTONIC, which stops meanwhile
+    let get_suspended = || -> io::Result<i64> {
+        let boottime = time::clock_gettime(nc::CLOCK_BOOTTIME)?;
+        let monotonic = time::clock_gettime(nc::CLOCK_MONOTONIC)?;
+        Ok(boottime.tv_sec - monotonic.tv_sec)
+    };
+    let suspended = Cell::new(get_suspended()?);
+
+    // after a jump every zone is looked up again, as the time may have gone back
+    let update_time = |jumped: bool| -> io::Result<()> {
+        seconds.set(get_time()?);
+        let now = seconds.get();
+        let mut current = offsets.get();
+        for (offset, zone) in current.iter_mut().zip(&*zones.borrow()) {
+            if jumped || now >= offset.until {
+                *offset = zone.lookup(now);
+            }
+        }
+        offsets.set(current);
+        Ok(())
+    };
+
+    ring.submit(5 + config_watch.is_some() as u32)?;
 
-    fn wait(ring: &IoUring, cb: &mut impl FnMut() -> io::Result<()>) -> io::Result<()> {
+    fn wait(ring: &EventLoop) -> io::Result<()> {
         loop {
             match ring.wait() {
-                Ok(_) => break Ok(()),
-                Err(x) if x == nc::EINTR => cb()?,
-                Err(x) => break Err(x),
+                Err(x) if x == nc::EINTR => {}
+                result => break result,
             }
         }
     }
 
-    loop {
-        wait(&ring, &mut redraw)?;
+    let status = 'run: loop {
+        wait(&ring)?;
         let cqe = ring.complete();
-        match cqe.user_data {
+        let queued = match cqe.user_data & 0xff {
+            x if x == Token::Timeout as _ && cqe.user_data >> 8 != generation.get() => 0,
+            x if x == Token::Timeout as _
+                && cqe.res == -nc::EINVAL
+                && realtime.get()
+                && matches!(options.get().mode, Mode::Clock) =>
+            {
+                realtime.set(false);
+                arm(&mut timeout)?;
+                1
+            }
+            // a timeout completes by expiring, anything else is an error
+            x if x == Token::Timeout as _ && cqe.res < 0 && cqe.res != -nc::ETIME => {
+                return Err(-cqe.res);
+            }
             x if x == Token::Timeout as _ => {
-                seconds.set(get_time()?);
+                centis.set(get_centis()?);
+                // the timer counts down whatever is shown, checked as often as
+                // that ticks
+                if timer.borrow_mut().tick(centis.get()) {
+                    FdWriter::stdout().write_all(bell!())?;
+                    if let Some(status) = options.get().exit_status {
+                        break status;
+                    }
+                }
+                match options.get().mode {
+                    Mode::Clock => {
+                        let was_suspended = suspended.replace(get_suspended()?);
+                        update_time(suspended.get() != was_suspended)?;
+                        redraw()?;
+                    }
+                    Mode::Stopwatch => {
+                        if stopwatch.borrow().is_running() {
+                            redraw()?;
+                        }
+                    }
+                    Mode::Timer(_) => {
+                        let frame = timer_frame_at(centis.get());
+                        if frame != timer_frame.get() {
+                            timer_frame.set(frame);
+                            redraw()?;
+                        }
+                    }
+                }
+                arm(&mut timeout)?;
+                1
+            }
+            // only a jump of the clock ends the read, anything else is an error
+            x if x == Token::ClockSet as _ && cqe.res != -nc::ECANCELED => {
+                return Err(if cqe.res < 0 { -cqe.res } else { nc::EIO });
+            }
+            x if x == Token::ClockSet as _ => {
+                // canceled by a jump: show the new time now and tick from it
+                arm_clock_set()?;
+                update_time(true)?;
                 redraw()?;
+                let clock = matches!(options.get().mode, Mode::Clock);
+                // the timeout armed before the jump waits for a second that
+                // is now far off or past: remove it, and skip what it returns
+                if clock {
+                    let stale = Token::Timeout as u64 | generation.get() << 8;
+                    ring.prepare_timeout_remove(stale, Token::TimeoutRemove as _);
+                    generation.set(generation.get() + 1);
+                    arm(&mut timeout)?;
+                }
+                ring.prepare_read(
+                    clock_set as _,
+                    unsafe { expirations.assume_init_mut() },
+                    Token::ClockSet as _,
+                );
+                1 + 2 * clock as u32
             }
-            x if x == Token::Read as _ => {
-                if cqe.res == 1 && [b'', b'q'].contains(&unsafe { input_buf.assume_init_ref() }[0])
-                {
-                    break;
+            // the stale timeout may have fired before its removal
+            x if x == Token::TimeoutRemove as _ && cqe.res < 0 && cqe.res != -nc::ENOENT => {
+                return Err(-cqe.res);
+            }
+            x if x == Token::TimeoutRemove as _ => 0,
+            x if x == Token::Signal as _ => {
+                let info = unsafe { siginfo.assume_init_ref() };
+                let signal = match cqe.res {
+                    1.. => u32::from_ne_bytes([info[0], info[1], info[2], info[3]]) as i32,
+                    _ => 0,
+                };
+                match signal {
+                    nc::SIGINT | nc::SIGTERM | nc::SIGHUP | nc::SIGQUIT => break 0,
+                    nc::SIGTSTP => {
+                        // leave the terminal as it was found, then stop until SIGCONT
+                        on_exit()?;
+                        unsafe { nc::kill(nc::getpid(), nc::SIGSTOP)? };
+                    }
+                    nc::SIGCONT => {
+                        // the terminal may have changed meanwhile
+                        on_enter()?;
+                        resize()?;
+                        repaint.set(true);
+                        redraw()?;
+                    }
+                    nc::SIGWINCH => {
+                        resize()?;
+                        redraw()?;
+                    }
+                    _ => {}
                 }
                 ring.prepare_read(
-                    io::STDIN as _,
-                    unsafe { input_buf.assume_init_mut() },
-                    Token::Read as _,
+                    signal_fd as _,
+                    unsafe { siginfo.assume_init_mut() },
+                    Token::Signal as _,
                 );
+                1
+            }
+            x if x == Token::Config as _ && cqe.res < 0 => 0,
+            x if x == Token::Config as _ => {
+                let read = unsafe { events.assume_init_ref() };
+                let changed = config::changed(&read[..(cqe.res as usize).min(read.len())]);
+                if let Some(path) = config.filter(|_| changed) {
+                    // the current settings still point into the other buffer
+                    let next = 1 - buffer.get();
+                    let mut message = config_message.borrow_mut();
+                    *message = fmt::Buf::new();
+                    match config::read(path, config_buffer(next)) {
+                        Ok(config) => match parse::args(env::args().skip(1), config) {
+                            Ok(Command::Run(mut new)) => {
+                                buffer.set(next);
+                                // a mode switched to stays
+                                new.mode = options.get().mode;
+                                let old = options.replace(new);
+                                settle(old.zones() != new.zones());
+                            }
+                            // `--help` and `--version` never get this far
+                            Ok(_) => {}
+                            // the file only fails with the options given, as
+                            // with too many bindings between them
+                            Err(e) => _ = write!(message, "{}: {e}", Bytes(path)),
+                        },
+                        // the previous settings stay
+                        Err(e) => _ = config_error(&mut *message, path, &e),
+                    }
+                    drop(message);
+                    resize()?;
+                    redraw()?;
+                }
+                ring.prepare_read(
+                    config_watch.unwrap_or_default() as _,
+                    unsafe { events.assume_init_mut() },
+                    Token::Config as _,
+                );
+                1
+            }
+            x if x == Token::Read as _ || x == Token::Escape as _ => {
+                if x == Token::Read as _ {
+                    let input = unsafe { input_buf.assume_init_ref() };
+                    decoder.push(&input[..(cqe.res.max(0) as usize).min(input.len())]);
+                } else {
+                    decoder.flush();
+                }
+                let mut queued = 1;
+                let mut acted = false;
+                while let Some(event) = decoder.next_event() {
+                    let key = match event {
+                        input::Event::Key(key) => key,
+                        input::Event::Sequence(sequence) => {
+                            if let Some(supported) = parse::mode_report(sequence, b"2026") {
+                                synchronized.set(supported);
+                            }
+                            continue;
+                        }
+                    };
+                    let Some(action) = options.get().keymap.action(key) else {
+                        continue;
+                    };
+                    acted = true;
+                    centis.set(get_centis()?);
+                    let now = centis.get();
+                    match (options.get().mode, action) {
+                        (_, Action::Quit) => break 'run 0,
+                        (Mode::Stopwatch, Action::StartStop) => stopwatch.borrow_mut().toggle(now),
+                        (Mode::Stopwatch, Action::Lap) => stopwatch.borrow_mut().lap(now),
+                        (Mode::Stopwatch, Action::Reset) => stopwatch.borrow_mut().reset(),
+                        (Mode::Timer(_), Action::StartStop) => timer.borrow_mut().toggle(now),
+                        (Mode::Timer(_), Action::AddMinute) => timer.borrow_mut().adjust(now, 6000),
+                        (Mode::Timer(_), Action::SubtractMinute) => {
+                            timer.borrow_mut().adjust(now, -6000)
+                        }
+                        (_, Action::ToggleSeconds) => {
+                            unsafe { LAYOUT.seconds = !LAYOUT.seconds };
+                            resize()?;
+                        }
+                        (_, Action::CycleTheme) => {
+                            theme.set((theme.get() + 1) % (THEMES.len() + 1))
+                        }
+                        (mode, Action::NextMode) => {
+                            let mode = match mode {
+                                Mode::Clock => Mode::Stopwatch,
+                                Mode::Stopwatch => after_stopwatch,
+                                Mode::Timer(_) => Mode::Clock,
+                            };
+                            let mut switched = options.get();
+                            switched.mode = mode;
+                            options.set(switched);
+                            settle(false);
+                            resize()?;
+                            // the clock's time may be a while old
+                            update_time(false)?;
+                            generation.set(generation.get() + 1);
+                            arm(&mut timeout)?;
+                            queued += 1;
+                        }
+                        // handled like the signal the terminal would have sent
+                        (_, Action::Suspend) => unsafe { nc::kill(nc::getpid(), nc::SIGTSTP)? },
+                        _ => {}
+                    }
+                    timer_frame.set(timer_frame_at(now));
+                }
+                if acted {
+                    redraw()?;
+                }
+                // restarted by each read that leaves input waiting
+                if decoder.is_waiting() {
+                    time::timerfd_settime(escape_timer, 0, &ESCAPE_TIMEOUT)?;
+                }
+                match x == Token::Read as _ {
+                    true => ring.prepare_read(
+                        io::STDIN as _,
+                        unsafe { input_buf.assume_init_mut() },
+                        Token::Read as _,
+                    ),
+                    false => ring.prepare_read(
+                        escape_timer as _,
+                        unsafe { escape_expirations.assume_init_mut() },
+                        Token::Escape as _,
+                    ),
+                }
+                queued
             }
             _ => utils::unreachable(),
-        }
-        ring.submit(1)?;
+        };
+        ring.submit(queued)?;
+    };
+    on_exit()?;
+    Ok(status)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[cfg_attr(not(test), unsafe(no_mangle))]
+#[naked]
+extern "C" fn _start() -> ! {
+    // hand the initial stack pointer (argc, argv, envp) to `start` on an aligned stack
+    unsafe { naked_asm!("mov rdi, rsp", "and rsp, -16", "call {}", sym start) }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[cfg_attr(not(test), unsafe(no_mangle))]
+#[naked]
+extern "C" fn _start() -> ! {
+    // the kernel leaves `sp` 16-byte aligned, and `x29`/`x30` zeroed to end backtraces
+    unsafe { naked_asm!("mov x0, sp", "bl {}", sym start) }
+}
+
+#[cfg(target_arch = "riscv64")]
+#[cfg_attr(not(test), unsafe(no_mangle))]
+#[naked]
+extern "C" fn _start() -> ! {
+    // `gp` is set up before anything may be linked to address data through it
+    unsafe {
+        naked_asm!(
+            ".option push",
+            ".option norelax",
+            "la gp, __global_pointer$",
+            ".option pop",
+            "mv a0, sp",
+            "call {}",
+            sym start,
+        )
+    }
+}
+
+#[cfg(target_arch = "x86")]
+#[cfg_attr(not(test), unsafe(no_mangle))]
+#[naked]
+extern "C" fn _start() -> ! {
+    // cdecl takes the stack pointer on the stack, 16-byte aligned at the call
+    unsafe {
+        naked_asm!(
+            "mov eax, esp",
+            "and esp, -16",
+            "sub esp, 12",
+            "push eax",
+            "call {}",
+            sym start,
+        )
     }
-    on_exit()
 }
 
+#[cfg(target_arch = "arm")]
 #[cfg_attr(not(test), unsafe(no_mangle))]
+#[naked]
 extern "C" fn _start() -> ! {
-    utils::exit(match main() {
-        Ok(_) => 0,
+    unsafe { naked_asm!("mov r0, sp", "bl {}", sym start) }
+}
+
+extern "C" fn start(sp: *const usize) -> ! {
+    unsafe { env::init(sp) };
+    let mut path = [0; config::PATH_MAX];
+    let path = config::path(&mut path);
+    let config = match path.map(|path| (path, config::read(path, config_buffer(0)))) {
+        Some((path, Err(e))) => {
+            eprint!("clock: ");
+            _ = config_error(&mut FdWriter::stderr(), path, &e);
+            eprint!("\n");
+            utils::exit(2)
+        }
+        Some((_, Ok(config))) => config,
+        None => Options::default(),
+    };
+    let options = match parse::args(env::args().skip(1), config) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Help) => {
+            print!("{}", parse::HELP);
+            utils::exit(0)
+        }
+        Ok(Command::Version) => {
+            print!("{}", parse::VERSION);
+            utils::exit(0)
+        }
+        Err(e) => {
+            eprint!("clock: {e}\nTry 'clock --help' for more information.\n");
+            utils::exit(2)
+        }
+    };
+    utils::exit(match main(options, path) {
+        Ok(status) => status as _,
         Err(e) => e as _,
     });
 }
@@ -396,3 +1201,22 @@ unsafe impl GlobalAlloc for GlobalAllocator {
 
     unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
 }
+
+#[test]
+fn test_layout() {
+    let width = |font: Font, text: &[u8]| font.width(&Text::new(&[text]));
+    let mut layout = unsafe { LAYOUT };
+    layout.fit(200, 50);
+    assert_eq!((layout.font, layout.chars), (Some(Font::Large), 8));
+    // seconds go before the font shrinks
+    let narrow = width(Font::Large, b"00:00:00") - 1;
+    layout.fit(narrow, 50);
+    assert_eq!((layout.font, layout.chars), (Some(Font::Large), 5));
+    let narrow = width(Font::Large, b"00:00") - 1;
+    layout.fit(narrow, 50);
+    assert_eq!((layout.font, layout.chars), (Some(Font::Compact), 5));
+    layout.fit(8, 1);
+    assert_eq!((layout.font, layout.chars), (Some(Font::Text), 5));
+    layout.fit(1, 1);
+    assert_eq!(layout.font, None);
+}
@@ -0,0 +1,758 @@
+use core::fmt::{self, Display};
+
+use crate::{
+    draw::{Font, Hours, Palette, Style, color::Color},
+    fmt::Bytes,
+    keymap::{Action, Key, Keymap, MAX_BINDINGS},
+};
+
+pub const HELP: &str = "\
+Usage: clock [OPTION]...
+A minimalist digital clock for the terminal.
+
+Options:
+  -z, --zone ZONE         show the time in ZONE, a zoneinfo name or POSIX TZ rule
+                          (defaults to $TZ, then /etc/localtime); repeat it to
+                          show up to 8 labeled clocks
+  -d, --date              show the date under the time
+      --date-format FORMAT
+                          format the date like strftime, e.g. '%A %F' or
+                          'week %V'; implies --date
+      --hours 12|24       use a 12-hour clock with AM/PM or a 24-hour one
+                          (defaults to what LC_TIME uses)
+      --no-leading-zero   leave out the leading zero of hours
+  -s, --stopwatch         run a stopwatch instead of showing the time
+  -t, --timer DURATION    count down from DURATION, e.g. 90, 25m or 1h30m,
+                          under 100h
+  -e, --exit-status N     exit with status N once the timer expires
+      --fg COLOR          color of the clock: a name such as red or bright-red,
+                          default, ansi:N or #rrggbb; prefix it with digits=,
+                          colon= or labels= to color only that element
+      --bg COLOR          background color, of the whole screen unless prefixed
+                          like for --fg
+      --font FONT         largest font to use: large, compact or text; smaller
+                          ones still take over on small terminals
+      --bind KEY=ACTION   bind KEY, such as x, ctrl-x, alt-up or f5, to ACTION:
+                          quit, start-stop, lap, reset, add-minute,
+                          subtract-minute, toggle-seconds, next-mode,
+                          cycle-theme or suspend
+  -h, --help              print this help and exit
+  -V, --version           print the version and exit
+
+Keys:
+  q, Esc, Ctrl-C          quit
+  space                   start or pause the stopwatch or timer,
+                          dismiss an expired timer
+  l, Enter                record a lap
+  r                       reset the stopwatch
+  +, -                    add or subtract a minute from the timer
+  s                       show or hide seconds
+  m                       switch to the stopwatch, to the timer if one was
+                          given, and back to the clock
+  t                       cycle through color themes
+  Ctrl-Z                  suspend
+
+Files:
+  $XDG_CONFIG_HOME/clock/config, or ~/.config/clock/config
+                          settings read before the options, which override
+                          them: 'option = value' lines for zone, date,
+                          date-format, hours, no-leading-zero, fg, bg, font
+                          and bind, with true or false for flags; # starts a
+                          comment. Edits apply at once, unless invalid
+";
+
+pub const VERSION: &str = concat!("clock ", env!("CARGO_PKG_VERSION"), "\n");
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum Mode {
+    #[default]
+    Clock,
+    Stopwatch,
+    /// Countdown from the given number of seconds.
+    Timer(u64),
+}
+
+pub const MAX_ZONES: usize = 8;
+
+pub const DATE_FORMAT: &[u8] = b"%a %d %b %Y";
+
+#[derive(Clone, Copy, Default)]
+pub struct Options {
+    zones: [&'static [u8]; MAX_ZONES],
+    zone_count: usize,
+    pub mode: Mode,
+    pub exit_status: Option<u8>,
+    pub palette: Palette,
+    /// Format of the date line, if shown.
+    pub date: Option<&'static [u8]>,
+    /// `None` to follow the locale.
+    pub hours: Option<Hours>,
+    pub no_leading_zero: bool,
+    pub font: Font,
+    pub keymap: Keymap,
+}
+
+impl Options {
+    /// Zones given with `--zone`, in order.
+    pub fn zones(&self) -> &[&'static [u8]] {
+        &self.zones[..self.zone_count]
+    }
+
+    /// Zones to label clocks with: none for a single clock.
+    pub fn labels(&self) -> &[&'static [u8]] {
+        match self.zones() {
+            zones @ [_, _, ..] => zones,
+            _ => &[],
+        }
+    }
+}
+
+// parsed once, and nothing to box it in without an allocator
+#[allow(clippy::large_enum_variant)]
+pub enum Command {
+    Run(Options),
+    Help,
+    Version,
+}
+
+#[derive(Clone, Copy)]
+enum Opt {
+    Help,
+    Version,
+    Zone,
+    Date,
+    DateFormat,
+    Hours,
+    NoLeadingZero,
+    Stopwatch,
+    Timer,
+    ExitStatus,
+    Fg,
+    Bg,
+    Font,
+    Bind,
+}
+
+impl Opt {
+    /// Whether the config file may set it: what the clock looks like, not
+    /// what it does.
+    fn in_config(self) -> bool {
+        matches!(
+            self,
+            Opt::Zone
+                | Opt::Date
+                | Opt::DateFormat
+                | Opt::Hours
+                | Opt::NoLeadingZero
+                | Opt::Fg
+                | Opt::Bg
+                | Opt::Font
+                | Opt::Bind
+        )
+    }
+}
+
+struct Spec {
+    short: u8,
+    long: &'static [u8],
+    opt: Opt,
+    takes_value: bool,
+}
+
+const SPECS: &[Spec] = &[
+    Spec {
+        short: b'z',
+        long: b"zone",
+        opt: Opt::Zone,
+        takes_value: true,
+    },
+    Spec {
+        short: b'd',
+        long: b"date",
+        opt: Opt::Date,
+        takes_value: false,
+    },
+    Spec {
+        short: 0,
+        long: b"date-format",
+        opt: Opt::DateFormat,
+        takes_value: true,
+    },
+    Spec {
+        short: 0,
+        long: b"hours",
+        opt: Opt::Hours,
+        takes_value: true,
+    },
+    Spec {
+        short: 0,
+        long: b"no-leading-zero",
+        opt: Opt::NoLeadingZero,
+        takes_value: false,
+    },
+    Spec {
+        short: b's',
+        long: b"stopwatch",
+        opt: Opt::Stopwatch,
+        takes_value: false,
+    },
+    Spec {
+        short: b't',
+        long: b"timer",
+        opt: Opt::Timer,
+        takes_value: true,
+    },
+    Spec {
+        short: b'e',
+        long: b"exit-status",
+        opt: Opt::ExitStatus,
+        takes_value: true,
+    },
+    Spec {
+        short: 0,
+        long: b"fg",
+        opt: Opt::Fg,
+        takes_value: true,
+    },
+    Spec {
+        short: 0,
+        long: b"bg",
+        opt: Opt::Bg,
+        takes_value: true,
+    },
+    Spec {
+        short: 0,
+        long: b"font",
+        opt: Opt::Font,
+        takes_value: true,
+    },
+    Spec {
+        short: 0,
+        long: b"bind",
+        opt: Opt::Bind,
+        takes_value: true,
+    },
+    Spec {
+        short: b'h',
+        long: b"help",
+        opt: Opt::Help,
+        takes_value: false,
+    },
+    Spec {
+        short: b'V',
+        long: b"version",
+        opt: Opt::Version,
+        takes_value: false,
+    },
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Name {
+    Short(u8),
+    Long(&'static [u8]),
+    /// A setting in the config file.
+    Key(&'static [u8]),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    Unknown(Name),
+    MissingValue(Name),
+    UnexpectedValue(Name),
+    InvalidValue(Name, &'static [u8]),
+    TooMany(Name, usize),
+    UnexpectedArgument(&'static [u8]),
+    /// A config line that is not `key = value`.
+    Syntax(&'static [u8]),
+}
+
+/// An error on a line of the config file, counted from 1.
+#[derive(Debug, PartialEq)]
+pub struct LineError {
+    pub line: usize,
+    pub error: Error,
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Name::Short(c) => write!(f, "-{}", c as char),
+            Name::Long(name) => write!(f, "--{}", Bytes(name)),
+            Name::Key(name) => write!(f, "{}", Bytes(name)),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::Unknown(name) => write!(f, "unrecognized option '{name}'"),
+            Error::MissingValue(name) => write!(f, "option '{name}' requires an argument"),
+            Error::UnexpectedValue(name) => write!(f, "option '{name}' doesn't allow an argument"),
+            Error::InvalidValue(name, value) => {
+                write!(f, "invalid argument '{}' for '{name}'", Bytes(value))
+            }
+            Error::TooMany(name, max) => write!(f, "option '{name}' given more than {max} times"),
+            Error::UnexpectedArgument(arg) => write!(f, "unexpected argument '{}'", Bytes(arg)),
+            Error::Syntax(line) => write!(f, "expected 'option = value', not '{}'", Bytes(line)),
+        }
+    }
+}
+
+impl Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.line, self.error)
+    }
+}
+
+/// Parses the command line, without the program name, over `config`.
+///
+/// Accepts `--long VALUE`, `--long=VALUE`, `-s VALUE`, `-sVALUE` and bundled
+/// short flags like `-hV`. `--help` and `--version` win over everything else.
+/// Zones given replace those of `config` rather than adding to them.
+pub fn args(
+    mut args: impl Iterator<Item = &'static [u8]>,
+    config: Options,
+) -> Result<Command, Error> {
+    let config_zones = config.zone_count;
+    let mut options = Options {
+        zone_count: 0,
+        ..config
+    };
+    let mut command = None;
+    while let Some(arg) = args.next() {
+        match arg {
+            [b'-', b'-', long @ ..] if !long.is_empty() => {
+                let (long, inline) = match long.iter().position(|&b| b == b'=') {
+                    Some(i) => (&long[..i], Some(&long[i + 1..])),
+                    None => (long, None),
+                };
+                let name = Name::Long(long);
+                let spec = SPECS
+                    .iter()
+                    .find(|spec| spec.long == long)
+                    .ok_or(Error::Unknown(name))?;
+                let value = match (spec.takes_value, inline) {
+                    (true, Some(value)) => Some(value),
+                    (true, None) => Some(args.next().ok_or(Error::MissingValue(name))?),
+                    (false, Some(_)) => return Err(Error::UnexpectedValue(name)),
+                    (false, None) => None,
+                };
+                apply(&mut options, &mut command, spec.opt, name, value)?;
+            }
+            [b'-', b'-'] => {
+                if let Some(arg) = args.next() {
+                    return Err(Error::UnexpectedArgument(arg));
+                }
+            }
+            [b'-', shorts @ ..] if !shorts.is_empty() => {
+                let mut shorts = shorts;
+                while let [short, rest @ ..] = shorts {
+                    let name = Name::Short(*short);
+                    let spec = SPECS
+                        .iter()
+                        .find(|spec| spec.short != 0 && spec.short == *short)
+                        .ok_or(Error::Unknown(name))?;
+                    shorts = rest;
+                    let value = match (spec.takes_value, shorts) {
+                        (true, []) => Some(args.next().ok_or(Error::MissingValue(name))?),
+                        (true, value) => {
+                            shorts = &[];
+                            Some(value)
+                        }
+                        (false, _) => None,
+                    };
+                    apply(&mut options, &mut command, spec.opt, name, value)?;
+                }
+            }
+            arg => return Err(Error::UnexpectedArgument(arg)),
+        }
+    }
+    if options.zone_count == 0 {
+        options.zone_count = config_zones;
+    }
+    Ok(command.unwrap_or(Command::Run(options)))
+}
+
+/// Parses a config file into `options`: `option = value` lines, each naming
+/// a long option that `Opt::in_config`, with `true` or `false` for flags.
+/// Blank lines and those starting with `#` are skipped.
+pub fn config(text: &'static [u8], options: &mut Options) -> Result<(), LineError> {
+    for (i, line) in text.split(|&b| b == b'\n').enumerate() {
+        let at = |error| LineError { line: i + 1, error };
+        let line = line.trim_ascii();
+        if line.is_empty() || line[0] == b'#' {
+            continue;
+        }
+        let Some(eq) = line.iter().position(|&b| b == b'=') else {
+            return Err(at(Error::Syntax(line)));
+        };
+        let key = line[..eq].trim_ascii();
+        let value = line[eq + 1..].trim_ascii();
+        let name = Name::Key(key);
+        let spec = SPECS
+            .iter()
+            .find(|spec| spec.long == key && spec.opt.in_config())
+            .ok_or_else(|| at(Error::Unknown(name)))?;
+        let value = match (spec.takes_value, value) {
+            (true, value) => Some(value),
+            (false, b"true") => None,
+            (false, b"false") => continue,
+            (false, value) => return Err(at(Error::InvalidValue(name, value))),
+        };
+        apply(options, &mut None, spec.opt, name, value).map_err(at)?;
+    }
+    Ok(())
+}
+
+fn apply(
+    options: &mut Options,
+    command: &mut Option<Command>,
+    opt: Opt,
+    name: Name,
+    value: Option<&'static [u8]>,
+) -> Result<(), Error> {
+    let value = value.unwrap_or_default();
+    match opt {
+        Opt::Help => _ = command.get_or_insert(Command::Help),
+        Opt::Version => _ = command.get_or_insert(Command::Version),
+        Opt::Zone if value.is_empty() => return Err(Error::InvalidValue(name, value)),
+        Opt::Zone if options.zone_count == MAX_ZONES => {
+            return Err(Error::TooMany(name, MAX_ZONES));
+        }
+        Opt::Zone => {
+            options.zones[options.zone_count] = value;
+            options.zone_count += 1;
+        }
+        Opt::Date => _ = options.date.get_or_insert(DATE_FORMAT),
+        Opt::DateFormat => options.date = Some(value),
+        Opt::Hours => {
+            options.hours = Some(match value {
+                b"12" => Hours::H12,
+                b"24" => Hours::H24,
+                _ => return Err(Error::InvalidValue(name, value)),
+            })
+        }
+        Opt::NoLeadingZero => options.no_leading_zero = true,
+        Opt::Font => {
+            options.font = match value {
+                b"large" => Font::Large,
+                b"compact" => Font::Compact,
+                b"text" => Font::Text,
+                _ => return Err(Error::InvalidValue(name, value)),
+            }
+        }
+        Opt::Bind => {
+            // the last `=`, as the key may be one
+            let i = value.iter().rposition(|&b| b == b'=');
+            let key = i.and_then(|i| Key::parse(&value[..i]));
+            let action = i.and_then(|i| Action::parse(&value[i + 1..]));
+            let (Some(key), Some(action)) = (key, action) else {
+                return Err(Error::InvalidValue(name, value));
+            };
+            if !options.keymap.bind(key, action) {
+                return Err(Error::TooMany(name, MAX_BINDINGS));
+            }
+        }
+        Opt::Stopwatch => options.mode = Mode::Stopwatch,
+        Opt::Timer => {
+            let seconds = duration(value).ok_or(Error::InvalidValue(name, value))?;
+            options.mode = Mode::Timer(seconds);
+        }
+        Opt::ExitStatus => {
+            let status = number(value)
+                .and_then(|n| u8::try_from(n).ok())
+                .ok_or(Error::InvalidValue(name, value))?;
+            options.exit_status = Some(status);
+        }
+        Opt::Fg | Opt::Bg => {
+            let (element, color) = match value.iter().position(|&b| b == b'=') {
+                Some(i) => (Some(&value[..i]), &value[i + 1..]),
+                None => (None, value),
+            };
+            let color = Color::parse(color).ok_or(Error::InvalidValue(name, value))?;
+            let palette = &mut options.palette;
+            if let (Opt::Bg, None) = (opt, element) {
+                palette.background = color;
+            }
+            let styles: &mut [&mut Style] = match element {
+                None => &mut [&mut palette.digits, &mut palette.colon, &mut palette.labels],
+                Some(b"digits") => &mut [&mut palette.digits],
+                Some(b"colon") => &mut [&mut palette.colon],
+                Some(b"labels") => &mut [&mut palette.labels],
+                Some(_) => return Err(Error::InvalidValue(name, value)),
+            };
+            for style in styles {
+                match opt {
+                    Opt::Fg => style.fg = color,
+                    _ => style.bg = color,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn number(s: &[u8]) -> Option<u64> {
+    if s.is_empty() {
+        return None;
+    }
+    s.iter().try_fold(0u64, |n, &b| match b {
+        b'0'..=b'9' => n.checked_mul(10)?.checked_add((b - b'0') as _),
+        _ => None,
+    })
+}
+
+/// Longest timer, so that its hours fit two digits.
+pub const MAX_DURATION: u64 = 100 * 3600;
+
+/// Parses a positive duration in seconds under `MAX_DURATION`: a bare number
+/// of seconds, or numbers suffixed with `h`, `m` and `s` such as `1h30m`.
+pub fn duration(s: &[u8]) -> Option<u64> {
+    if let Some(seconds) = number(s) {
+        return (1..MAX_DURATION).contains(&seconds).then_some(seconds);
+    }
+    let mut seconds = 0u64;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+        let n = number(&rest[..digits])?;
+        let unit = match rest.get(digits)? {
+            b'h' => 3600,
+            b'm' => 60,
+            b's' => 1,
+            _ => return None,
+        };
+        seconds = n.checked_mul(unit)?.checked_add(seconds)?;
+        rest = &rest[digits + 1..];
+    }
+    (1..MAX_DURATION).contains(&seconds).then_some(seconds)
+}
+
+/// Looks in `input` for the terminal's reply to a DECRQM query of private
+/// `mode`, `CSI ? mode ; Ps $ y`: whether the mode is supported.
+pub fn mode_report(input: &[u8], mode: &[u8]) -> Option<bool> {
+    let mut rest = input;
+    while let Some(start) = rest.iter().position(|&b| b == 0x1b) {
+        rest = &rest[start + 1..];
+        let report = rest
+            .strip_prefix(b"[?")
+            .and_then(|report| report.strip_prefix(mode))
+            .and_then(|report| report.strip_prefix(b";"));
+        // 1 and 2 for set and reset, 0 for unknown, 3 and 4 when fixed
+        if let Some([status, b'$', b'y', ..]) = report {
+            return Some(matches!(status, b'1' | b'2'));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+fn parse(args: &[&'static [u8]]) -> Result<Command, Error> {
+    self::args(args.iter().copied(), Options::default())
+}
+
+#[test]
+fn test_args() {
+    let zones = |args, expected: &[&[u8]]| match parse(args) {
+        Ok(Command::Run(options)) => assert_eq!(options.zones(), expected),
+        _ => panic!(),
+    };
+    zones(&[], &[]);
+    zones(&[b"--zone", b"UTC"], &[b"UTC"]);
+    zones(&[b"--zone=UTC"], &[b"UTC"]);
+    zones(&[b"-zUTC"], &[b"UTC"]);
+    zones(&[b"-z", b"UTC", b"--"], &[b"UTC"]);
+    zones(&[b"-z", b"UTC", b"-zAsia/Tokyo"], &[b"UTC", b"Asia/Tokyo"]);
+    assert!(matches!(parse(&[b"-z", b"UTC", b"-h"]), Ok(Command::Help)));
+    assert!(matches!(parse(&[b"--version"]), Ok(Command::Version)));
+    let mode = |args| match parse(args) {
+        Ok(Command::Run(options)) => options.mode,
+        _ => panic!(),
+    };
+    assert_eq!(mode(&[b"-sz", b"UTC"]), Mode::Stopwatch);
+    let date = |args| match parse(args) {
+        Ok(Command::Run(options)) => options.date,
+        _ => panic!(),
+    };
+    assert_eq!(date(&[]), None);
+    assert_eq!(date(&[b"-d"]), Some(DATE_FORMAT));
+    assert_eq!(date(&[b"--date-format=%F", b"--date"]), Some(&b"%F"[..]));
+    assert!(matches!(
+        parse(&[b"--hours", b"12", b"--no-leading-zero"]),
+        Ok(Command::Run(Options {
+            hours: Some(Hours::H12),
+            no_leading_zero: true,
+            ..
+        }))
+    ));
+    assert_eq!(mode(&[b"--timer", b"25m"]), Mode::Timer(1500));
+    assert!(matches!(
+        parse(&[b"-t90", b"-e", b"3"]),
+        Ok(Command::Run(Options {
+            mode: Mode::Timer(90),
+            exit_status: Some(3),
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn test_colors() {
+    use crate::draw::color::Literal;
+
+    let palette = |args| match parse(args) {
+        Ok(Command::Run(options)) => options.palette,
+        _ => panic!(),
+    };
+    let p = palette(&[
+        b"--fg",
+        b"red",
+        b"--fg=colon=#ffffff",
+        b"--bg",
+        b"labels=ansi:8",
+    ]);
+    assert!(p.digits.fg == Color::Normal(Literal::Red));
+    assert!(p.labels.fg == Color::Normal(Literal::Red));
+    assert!(
+        p.colon.fg
+            == Color::Rgb {
+                r: 255,
+                g: 255,
+                b: 255
+            }
+    );
+    assert!(p.labels.bg == Color::Ansi(8));
+    assert!(p.digits.bg == Color::Clear && p.background == Color::Clear);
+    let p = palette(&[b"--bg", b"bright-black"]);
+    assert!(p.background == Color::Bright(Literal::Black) && p.colon.bg == p.background);
+    let err = |args| parse(args).err().unwrap();
+    assert_eq!(
+        err(&[b"--fg", b"digits=purple"]),
+        Error::InvalidValue(Name::Long(b"fg"), b"digits=purple")
+    );
+    assert_eq!(
+        err(&[b"--bg=hours=red"]),
+        Error::InvalidValue(Name::Long(b"bg"), b"hours=red")
+    );
+}
+
+#[test]
+fn test_duration() {
+    assert_eq!(duration(b"90"), Some(90));
+    assert_eq!(duration(b"90s"), Some(90));
+    assert_eq!(duration(b"25m"), Some(1500));
+    assert_eq!(duration(b"1h30m15s"), Some(5415));
+    assert_eq!(duration(b"0"), None);
+    assert_eq!(duration(b"0m"), None);
+    assert_eq!(duration(b"m"), None);
+    assert_eq!(duration(b"5x"), None);
+    assert_eq!(duration(b"5m3"), None);
+    assert_eq!(duration(b""), None);
+    assert_eq!(duration(b"30h"), Some(108_000));
+    assert_eq!(duration(b"99h59m59s"), Some(MAX_DURATION - 1));
+    assert_eq!(duration(b"100h"), None);
+    assert_eq!(duration(b"18446744073709551615"), None);
+}
+
+#[test]
+fn test_errors() {
+    let err = |args| parse(args).err().unwrap();
+    assert_eq!(
+        err(&[b"--frobnicate"]),
+        Error::Unknown(Name::Long(b"frobnicate"))
+    );
+    assert_eq!(err(&[b"-x"]), Error::Unknown(Name::Short(b'x')));
+    assert_eq!(err(&[b"--zone"]), Error::MissingValue(Name::Long(b"zone")));
+    assert_eq!(err(&[b"-z"]), Error::MissingValue(Name::Short(b'z')));
+    assert_eq!(
+        err(&[b"--help=x"]),
+        Error::UnexpectedValue(Name::Long(b"help"))
+    );
+    assert_eq!(
+        err(&[b"--zone="]),
+        Error::InvalidValue(Name::Long(b"zone"), b"")
+    );
+    assert_eq!(err(&[b"now"]), Error::UnexpectedArgument(b"now"));
+    assert_eq!(
+        err(&[
+            b"-zA", b"-zB", b"-zC", b"-zD", b"-zE", b"-zF", b"-zG", b"-zH", b"-zI"
+        ]),
+        Error::TooMany(Name::Short(b'z'), MAX_ZONES)
+    );
+    assert_eq!(
+        err(&[b"--hours=13"]),
+        Error::InvalidValue(Name::Long(b"hours"), b"13")
+    );
+    assert_eq!(
+        err(&[b"-e", b"256"]),
+        Error::InvalidValue(Name::Short(b'e'), b"256")
+    );
+    assert_eq!(
+        err(&[b"--bind=q=fly"]),
+        Error::InvalidValue(Name::Long(b"bind"), b"q=fly")
+    );
+}
+
+#[test]
+fn test_mode_report() {
+    assert_eq!(mode_report(b"\x1b[?2026;2$y", b"2026"), Some(true));
+    assert_eq!(mode_report(b"q\x1b[?2026;1$yq", b"2026"), Some(true));
+    assert_eq!(mode_report(b"\x1b[?2026;0$y", b"2026"), Some(false));
+    assert_eq!(mode_report(b"\x1b[?1049;2$y", b"2026"), None);
+    assert_eq!(mode_report(b"\x1b", b"2026"), None);
+    assert_eq!(mode_report(b"q", b"2026"), None);
+}
+
+#[test]
+fn test_config() {
+    use crate::draw::color::Literal;
+
+    let mut config = Options::default();
+    let text = b"# clock\n\nzone = UTC\nzone=Asia/Tokyo\n  fg = digits=red  \ndate = true\n\
+        no-leading-zero = false\nhours = 12\nfont = compact\nbind = ==reset\n";
+    assert_eq!(self::config(text, &mut config), Ok(()));
+    assert_eq!(config.zones(), [&b"UTC"[..], b"Asia/Tokyo"]);
+    assert_eq!(config.date, Some(DATE_FORMAT));
+    assert!(!config.no_leading_zero);
+    assert_eq!(config.font, Font::Compact);
+    let key = |name| Key::parse(name).unwrap();
+    assert_eq!(config.keymap.action(key(b"=")), Some(Action::Reset));
+    // options override the file, and their zones replace its zones
+    let options = |args: &[&'static [u8]]| {
+        let mut config = Options::default();
+        self::config(text, &mut config).unwrap();
+        match self::args(args.iter().copied(), config) {
+            Ok(Command::Run(options)) => options,
+            _ => panic!(),
+        }
+    };
+    let o = options(&[b"--hours=24", b"--fg", b"colon=blue"]);
+    assert_eq!(o.zones(), [&b"UTC"[..], b"Asia/Tokyo"]);
+    assert_eq!(o.hours, Some(Hours::H24));
+    assert!(o.palette.digits.fg == Color::Normal(Literal::Red));
+    assert!(o.palette.colon.fg == Color::Normal(Literal::Blue));
+    let o = options(&[b"--bind", b"f5=next-mode"]);
+    assert_eq!(o.keymap.action(key(b"f5")), Some(Action::NextMode));
+    assert_eq!(o.keymap.action(key(b"=")), Some(Action::Reset));
+    assert_eq!(
+        options(&[b"-z", b"Europe/Paris"]).zones(),
+        [b"Europe/Paris"]
+    );
+
+    let err = |text| self::config(text, &mut Options::default()).err().unwrap();
+    let at = |line, error| LineError { line, error };
+    assert_eq!(err(b"zone = UTC\nzone"), at(2, Error::Syntax(b"zone")));
+    assert_eq!(
+        err(b"\n\ntimer = 5m"),
+        at(3, Error::Unknown(Name::Key(b"timer")))
+    );
+    assert_eq!(
+        err(b"date = yes"),
+        at(1, Error::InvalidValue(Name::Key(b"date"), b"yes"))
+    );
+    assert_eq!(
+        err(b"font = huge"),
+        at(1, Error::InvalidValue(Name::Key(b"font"), b"huge"))
+    );
+}
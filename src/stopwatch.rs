@@ -0,0 +1,153 @@
+use crate::fmt::Buf;
+
+/// Number of most recent laps kept.
+pub const MAX_LAPS: usize = 32;
+
+/// Times are in centiseconds of `CLOCK_MONOTONIC`.
+pub struct Stopwatch {
+    /// When the current run started, `None` while paused.
+    started: Option<u64>,
+    /// Time accumulated by previous runs.
+    accumulated: u64,
+    laps: [Lap; MAX_LAPS],
+    lap_count: usize,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Lap {
+    pub number: usize,
+    pub split: u64,
+    pub total: u64,
+}
+
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stopwatch {
+    pub const fn new() -> Self {
+        Self {
+            started: None,
+            accumulated: 0,
+            laps: [Lap {
+                number: 0,
+                split: 0,
+                total: 0,
+            }; MAX_LAPS],
+            lap_count: 0,
+        }
+    }
+
+    pub const fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn elapsed(&self, now: u64) -> u64 {
+        self.accumulated + self.started.map_or(0, |started| now - started)
+    }
+
+    pub fn toggle(&mut self, now: u64) {
+        self.started = match self.started {
+            Some(started) => {
+                self.accumulated += now - started;
+                None
+            }
+            None => Some(now),
+        };
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Records a lap, only while running.
+    pub fn lap(&mut self, now: u64) {
+        if self.started.is_none() {
+            return;
+        }
+        let total = self.elapsed(now);
+        let previous = self.laps().next().map_or(0, |lap| lap.total);
+        self.lap_count += 1;
+        self.laps[self.lap_count % MAX_LAPS] = Lap {
+            number: self.lap_count,
+            split: total - previous,
+            total,
+        };
+    }
+
+    /// Laps from the most recent one backwards.
+    pub fn laps(&self) -> impl Iterator<Item = &Lap> {
+        let n = self.lap_count;
+        (n.saturating_sub(MAX_LAPS - 1)..=n)
+            .rev()
+            .take_while(|&i| i > 0)
+            .map(|i| &self.laps[i % MAX_LAPS])
+    }
+}
+
+/// Writes centiseconds as `MM:SS.cc`, or `H:MM:SS.cc` from an hour on.
+pub struct Centis(pub u64);
+
+impl Centis {
+    /// Writes the time into `buf`.
+    pub fn write_to<const N: usize>(&self, buf: &mut Buf<N>) {
+        let seconds = self.0 / 100;
+        let (h, min, s, cs) = (
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            self.0 % 100,
+        );
+        if h > 0 {
+            buf.push_number(h as _, 0, b'0');
+            buf.push(b":");
+        }
+        buf.push_number(min as _, 2, b'0');
+        buf.push(b":");
+        buf.push_number(s as _, 2, b'0');
+        buf.push(b".");
+        buf.push_number(cs as _, 2, b'0');
+    }
+}
+
+#[test]
+fn test_stopwatch() {
+    let mut sw = Stopwatch::new();
+    sw.toggle(100);
+    sw.lap(350);
+    sw.toggle(400);
+    assert_eq!(sw.elapsed(1000), 300);
+    sw.lap(1000);
+    sw.toggle(2000);
+    sw.lap(2100);
+    let laps = sw.laps().map(|lap| (lap.number, lap.split, lap.total));
+    assert!(laps.eq([(2, 150, 400), (1, 250, 250)]));
+    sw.reset();
+    assert_eq!(sw.elapsed(3000), 0);
+    assert_eq!(sw.laps().count(), 0);
+}
+
+#[test]
+fn test_lap_ring() {
+    let mut sw = Stopwatch::new();
+    sw.toggle(0);
+    for i in 1..=MAX_LAPS as u64 + 5 {
+        sw.lap(i * 10);
+    }
+    assert_eq!(sw.laps().count(), MAX_LAPS);
+    assert_eq!(sw.laps().next().unwrap().number, MAX_LAPS + 5);
+}
+
+#[test]
+fn test_centis() {
+    let centis = |n| {
+        let mut buf = Buf::<16>::new();
+        Centis(n).write_to(&mut buf);
+        buf
+    };
+    assert_eq!(centis(0).as_bytes(), b"00:00.00");
+    assert_eq!(centis(6_012).as_bytes(), b"01:00.12");
+    assert_eq!(centis(12 * 360_000 + 5).as_bytes(), b"12:00:00.05");
+}
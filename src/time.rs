@@ -0,0 +1,52 @@
+use crate::io;
+
+/// `struct __kernel_timespec`: 64-bit seconds on every architecture, so the
+/// clock keeps going past 2038 on 32-bit ones.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Timespec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
+/// `struct __kernel_itimerspec`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Itimerspec {
+    pub it_interval: Timespec,
+    pub it_value: Timespec,
+}
+
+// 32-bit targets keep the old calls for `timespec`s with 32-bit seconds
+#[cfg(target_pointer_width = "64")]
+use nc::{SYS_CLOCK_GETTIME, SYS_TIMERFD_SETTIME};
+#[cfg(target_pointer_width = "32")]
+use nc::{SYS_CLOCK_GETTIME64 as SYS_CLOCK_GETTIME, SYS_TIMERFD_SETTIME64 as SYS_TIMERFD_SETTIME};
+
+pub fn clock_gettime(clock: i32) -> io::Result<Timespec> {
+    let mut time = Timespec::default();
+    unsafe { nc::syscalls::syscall2(SYS_CLOCK_GETTIME, clock as _, &raw mut time as _)? };
+    Ok(time)
+}
+
+/// Arms `fd`, or disarms it with a zero `it_value`.
+pub fn timerfd_settime(fd: i32, flags: i32, spec: &Itimerspec) -> io::Result<()> {
+    unsafe {
+        nc::syscalls::syscall4(
+            SYS_TIMERFD_SETTIME,
+            fd as _,
+            flags as _,
+            spec as *const _ as _,
+            0,
+        )
+        .map(drop)
+    }
+}
+
+#[test]
+fn test_clock_gettime() {
+    assert_eq!(size_of::<Timespec>(), 16);
+    let time = clock_gettime(nc::CLOCK_REALTIME).unwrap();
+    assert!(time.tv_sec > 1_700_000_000);
+    assert!((0..1_000_000_000).contains(&time.tv_nsec));
+}
@@ -0,0 +1,143 @@
+use crate::parse::MAX_DURATION;
+
+/// Countdown timer. Times are in centiseconds of `CLOCK_MONOTONIC`.
+pub struct Timer {
+    /// When the countdown reaches zero, `None` while paused.
+    deadline: Option<u64>,
+    /// Time left while paused.
+    remaining: u64,
+    /// When the countdown reached zero, until the alert is dismissed.
+    expired_at: Option<u64>,
+}
+
+impl Timer {
+    /// A running countdown of `duration` from `now`.
+    pub const fn new(duration: u64, now: u64) -> Self {
+        Self {
+            deadline: Some(now + duration),
+            remaining: duration,
+            expired_at: None,
+        }
+    }
+
+    pub fn remaining(&self, now: u64) -> u64 {
+        match self.deadline {
+            Some(deadline) => deadline.saturating_sub(now),
+            None => self.remaining,
+        }
+    }
+
+    /// Remaining whole seconds, rounded up so zero is only shown once expired.
+    pub fn remaining_seconds(&self, now: u64) -> u64 {
+        self.remaining(now).div_ceil(100)
+    }
+
+    pub const fn is_running(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    /// Centiseconds since expiry while the alert is active.
+    pub fn alert(&self, now: u64) -> Option<u64> {
+        self.expired_at.map(|expired_at| now - expired_at)
+    }
+
+    /// Advances to `now`, returns whether the countdown has just expired.
+    pub fn tick(&mut self, now: u64) -> bool {
+        match self.deadline {
+            Some(deadline) if deadline <= now => {
+                self.deadline = None;
+                self.remaining = 0;
+                self.expired_at = Some(deadline);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Pauses or resumes, or dismisses the alert once expired.
+    pub fn toggle(&mut self, now: u64) {
+        if self.expired_at.take().is_some() {
+            return;
+        }
+        match self.deadline.take() {
+            Some(deadline) => self.remaining = deadline.saturating_sub(now),
+            None if self.remaining > 0 => self.deadline = Some(now + self.remaining),
+            None => {}
+        }
+    }
+
+    /// Adds or subtracts time, restarting the countdown if added after expiry.
+    /// What is left stays under `MAX_DURATION`.
+    pub fn adjust(&mut self, now: u64, delta: i64) {
+        let remaining =
+            (self.remaining(now).saturating_add_signed(delta)).min((MAX_DURATION - 1) * 100);
+        if self.expired_at.is_some() {
+            if delta <= 0 {
+                return;
+            }
+            self.expired_at = None;
+            self.deadline = Some(now);
+        }
+        match &mut self.deadline {
+            Some(deadline) => *deadline = now + remaining,
+            None => self.remaining = remaining,
+        }
+    }
+}
+
+#[test]
+fn test_timer() {
+    let mut timer = Timer::new(1000, 0);
+    assert!(!timer.tick(500));
+    assert_eq!(timer.remaining_seconds(501), 5);
+    timer.toggle(600);
+    assert_eq!(timer.remaining(5000), 400);
+    timer.adjust(5000, -6000);
+    assert_eq!(timer.remaining(5000), 0);
+    timer.adjust(5000, 6000);
+    timer.toggle(5000);
+    assert!(!timer.tick(10999));
+    assert_eq!(timer.remaining_seconds(10999), 1);
+    assert!(timer.tick(11001));
+    assert_eq!(timer.alert(11100), Some(100));
+    assert_eq!(timer.remaining(11100), 0);
+}
+
+#[test]
+fn test_alert() {
+    let mut timer = Timer::new(100, 0);
+    assert!(timer.tick(100));
+    assert!(!timer.tick(200));
+    timer.adjust(300, -6000);
+    assert!(timer.alert(300).is_some());
+    timer.adjust(300, 6000);
+    assert!(timer.alert(300).is_none());
+    assert_eq!(timer.remaining(400), 5900);
+    assert!(timer.tick(6300));
+    timer.toggle(6400);
+    assert!(timer.alert(6400).is_none());
+    timer.toggle(6500);
+    assert!(!timer.is_running());
+}
+
+#[test]
+fn test_long_timer() {
+    let mut timer = Timer::new(30 * 3600 * 100, 0);
+    assert_eq!(timer.remaining_seconds(100), 30 * 3600 - 1);
+    for _ in 0..100 * 60 {
+        timer.adjust(100, 6000);
+    }
+    assert_eq!(timer.remaining_seconds(100), MAX_DURATION - 1);
+}
+
+#[test]
+fn test_expire_unseen() {
+    // switched away from, the timer is only ticked by the clock's seconds
+    let mut timer = Timer::new(250, 0);
+    assert!(!timer.tick(100));
+    assert!(!timer.tick(200));
+    assert!(timer.tick(300));
+    assert!(!timer.tick(400));
+    assert_eq!(timer.alert(400), Some(150));
+    assert_eq!(timer.remaining_seconds(400), 0);
+}
@@ -0,0 +1,283 @@
+use crate::io::{self, FdReader};
+use posix::Rule;
+
+pub mod posix;
+
+pub const LOCALTIME: &[u8] = b"/etc/localtime";
+pub const ZONEINFO: &[u8] = b"/usr/share/zoneinfo/";
+pub const TZIF_MAX: usize = 8192;
+
+/// UTC offset in effect from some instant until `until` (exclusive).
+#[derive(Clone, Copy)]
+pub struct Offset {
+    pub utc_offset: i32,
+    pub until: i64,
+}
+
+impl Offset {
+    pub const UTC: Self = Self {
+        utc_offset: 0,
+        until: i64::MAX,
+    };
+}
+
+/// A parsed TZif file (RFC 8536). Only the newest data block is kept.
+pub struct TzIf<'a> {
+    time_size: usize,
+    transitions: &'a [u8],
+    indices: &'a [u8],
+    types: &'a [u8],
+    /// Rule for instants past the last transition (v2+ footer).
+    footer: Option<Rule>,
+}
+
+pub enum Zone<'a> {
+    Utc,
+    TzIf(TzIf<'a>),
+    Posix(Rule),
+}
+
+impl Zone<'_> {
+    pub fn lookup(&self, t: i64) -> Offset {
+        match self {
+            Zone::Utc => Offset::UTC,
+            Zone::TzIf(tzif) => tzif.lookup(t),
+            Zone::Posix(rule) => rule.lookup(t),
+        }
+    }
+}
+
+const HEADER_SIZE: usize = 44;
+const TTINFO_SIZE: usize = 6;
+
+struct Header {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let header = data.get(..HEADER_SIZE)?;
+        if &header[..4] != b"TZif" {
+            return None;
+        }
+        let count = |i: usize| be_u32(&header[20 + i * 4..]) as usize;
+        Some(Self {
+            version: header[4],
+            isutcnt: count(0),
+            isstdcnt: count(1),
+            leapcnt: count(2),
+            timecnt: count(3),
+            typecnt: count(4),
+            charcnt: count(5),
+        })
+    }
+
+    const fn block_size(&self, time_size: usize) -> usize {
+        self.timecnt * time_size
+            + self.timecnt
+            + self.typecnt * TTINFO_SIZE
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn be_i64(bytes: &[u8]) -> i64 {
+    i64::from_be_bytes([
+        bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+    ])
+}
+
+impl<'a> TzIf<'a> {
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let mut header = Header::parse(data)?;
+        let mut block = &data[HEADER_SIZE..];
+        let mut time_size = 4;
+        if header.version >= b'2' {
+            // skip the legacy 32-bit block, the 64-bit one follows with its own header
+            block = block.get(header.block_size(4)..)?;
+            header = Header::parse(block)?;
+            block = &block[HEADER_SIZE..];
+            time_size = 8;
+        }
+        if header.typecnt == 0 || block.len() < header.block_size(time_size) {
+            return None;
+        }
+        // the footer is the TZ string between two newlines after the data block
+        let footer = match &block[header.block_size(time_size)..] {
+            [b'\n', rest @ ..] if time_size == 8 => rest
+                .iter()
+                .position(|&b| b == b'\n')
+                .and_then(|end| Rule::parse(&rest[..end])),
+            _ => None,
+        };
+        let (transitions, block) = block.split_at(header.timecnt * time_size);
+        let (indices, block) = block.split_at(header.timecnt);
+        let types = &block[..header.typecnt * TTINFO_SIZE];
+        if indices.iter().any(|&i| i as usize >= header.typecnt) {
+            return None;
+        }
+        Some(Self {
+            time_size,
+            transitions,
+            indices,
+            types,
+            footer,
+        })
+    }
+
+    fn transition(&self, i: usize) -> i64 {
+        let bytes = &self.transitions[i * self.time_size..];
+        match self.time_size {
+            4 => be_u32(bytes) as i32 as i64,
+            _ => be_i64(bytes),
+        }
+    }
+
+    fn utc_offset(&self, ty: u8) -> i32 {
+        be_u32(&self.types[ty as usize * TTINFO_SIZE..]) as i32
+    }
+
+    fn transition_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Looks up the offset in effect at `t` (seconds since the epoch).
+    pub fn lookup(&self, t: i64) -> Offset {
+        // number of transitions at or before `t`
+        let (mut lo, mut hi) = (0, self.transition_count());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.transition(mid) <= t {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        if lo == self.transition_count()
+            && let Some(footer) = &self.footer
+        {
+            return footer.lookup(t);
+        }
+        let ty = match lo {
+            0 => 0,
+            n => self.indices[n - 1],
+        };
+        Offset {
+            utc_offset: self.utc_offset(ty),
+            until: match lo < self.transition_count() {
+                true => self.transition(lo),
+                false => i64::MAX,
+            },
+        }
+    }
+}
+
+/// Reads a TZif file into `buf`.
+pub fn load<'a>(path: &[u8], buf: &'a mut [u8]) -> io::Result<TzIf<'a>> {
+    let file = FdReader::open(path)?;
+    let len = file.read_to_end(buf);
+    _ = file.close();
+    TzIf::parse(&buf[..len?]).ok_or(nc::EINVAL)
+}
+
+/// Reads `name` from the system zoneinfo directory, or as a path if absolute.
+pub fn load_named<'a>(name: &[u8], buf: &'a mut [u8]) -> io::Result<TzIf<'a>> {
+    if name.first() == Some(&b'/') {
+        return load(name, buf);
+    }
+    if name.split(|&b| b == b'/').any(|part| part == b"..") {
+        return Err(nc::EINVAL);
+    }
+    let mut path = [0; 256];
+    let len = ZONEINFO.len() + name.len();
+    if len > path.len() {
+        return Err(nc::ENAMETOOLONG);
+    }
+    path[..ZONEINFO.len()].copy_from_slice(ZONEINFO);
+    path[ZONEINFO.len()..len].copy_from_slice(name);
+    load(&path[..len], buf)
+}
+
+/// Resolves a zone the way libc resolves `TZ`: a zoneinfo name or path, a
+/// POSIX rule string, or `/etc/localtime` when unset. Anything that fails to
+/// load or parse falls back to UTC.
+pub fn resolve<'a>(tz: Option<&[u8]>, buf: &'a mut [u8]) -> Zone<'a> {
+    let Some(tz) = tz else {
+        return load(LOCALTIME, buf).map_or(Zone::Utc, Zone::TzIf);
+    };
+    // a leading colon means the value is a file, otherwise it may also be a rule string
+    let (name, file_only) = match tz {
+        [b':', name @ ..] => (name, true),
+        name => (name, false),
+    };
+    if name.is_empty() {
+        return Zone::Utc;
+    }
+    match load_named(name, buf) {
+        Ok(tzif) => Zone::TzIf(tzif),
+        Err(_) if !file_only => Rule::parse(name).map_or(Zone::Utc, Zone::Posix),
+        Err(_) => Zone::Utc,
+    }
+}
+
+#[test]
+fn test_lookup() {
+    // v1 file: UTC+1 until 1000, UTC+2 until 2000, UTC+1 afterwards
+    let mut data = [0u8; HEADER_SIZE + 2 * 4 + 2 + 2 * TTINFO_SIZE];
+    data[..5].copy_from_slice(b"TZif\0");
+    data[32..36].copy_from_slice(&2u32.to_be_bytes());
+    data[36..40].copy_from_slice(&2u32.to_be_bytes());
+    let body = &mut data[HEADER_SIZE..];
+    body[0..4].copy_from_slice(&1000u32.to_be_bytes());
+    body[4..8].copy_from_slice(&2000u32.to_be_bytes());
+    body[8..10].copy_from_slice(&[1, 0]);
+    body[10..14].copy_from_slice(&3600u32.to_be_bytes());
+    body[16..20].copy_from_slice(&7200u32.to_be_bytes());
+    let tz = TzIf::parse(&data).unwrap();
+    let at = |t| {
+        let Offset { utc_offset, until } = tz.lookup(t);
+        (utc_offset, until)
+    };
+    assert_eq!(at(0), (3600, 1000));
+    assert_eq!(at(999), (3600, 1000));
+    assert_eq!(at(1000), (7200, 2000));
+    assert_eq!(at(2000), (3600, i64::MAX));
+}
+
+#[test]
+fn test_footer() {
+    // v2 file: UTC+1 from 1000 on, then the footer's CET rules
+    const FOOTER: &[u8] = b"\nCET-1CEST,M3.5.0,M10.5.0/3\n";
+    let mut data = [0u8; 2 * HEADER_SIZE + TTINFO_SIZE + 8 + 1 + TTINFO_SIZE + FOOTER.len()];
+    data[..5].copy_from_slice(b"TZif2");
+    data[36..40].copy_from_slice(&1u32.to_be_bytes());
+    let body = &mut data[HEADER_SIZE + TTINFO_SIZE..];
+    body[..5].copy_from_slice(b"TZif2");
+    body[32..36].copy_from_slice(&1u32.to_be_bytes());
+    body[36..40].copy_from_slice(&1u32.to_be_bytes());
+    let body = &mut body[HEADER_SIZE..];
+    body[..8].copy_from_slice(&1000i64.to_be_bytes());
+    body[9..13].copy_from_slice(&3600u32.to_be_bytes());
+    body[15..].copy_from_slice(FOOTER);
+    let tz = TzIf::parse(&data).unwrap();
+    let at = |t| {
+        let Offset { utc_offset, until } = tz.lookup(t);
+        (utc_offset, until)
+    };
+    assert_eq!(at(999), (3600, 1000));
+    // 2026-03-29 01:00 UTC
+    assert_eq!(at(1774746000 - 1), (3600, 1774746000));
+    assert_eq!(at(1774746000).0, 7200);
+}
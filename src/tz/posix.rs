@@ -0,0 +1,288 @@
+use super::Offset;
+use crate::fmt::{civil_from_days, days_from_civil, is_leap};
+
+/// A POSIX TZ rule string such as `CET-1CEST,M3.5.0,M10.5.0/3`.
+#[derive(Clone, Copy)]
+pub struct Rule {
+    std_offset: i32,
+    dst: Option<Dst>,
+}
+
+#[derive(Clone, Copy)]
+struct Dst {
+    offset: i32,
+    start: Transition,
+    end: Transition,
+}
+
+#[derive(Clone, Copy)]
+struct Transition {
+    date: Date,
+    /// Seconds after local midnight, may be negative or exceed a day.
+    time: i32,
+}
+
+#[derive(Clone, Copy)]
+enum Date {
+    /// `Jn`: 1-based day of the year, February 29 is never counted.
+    Julian(u16),
+    /// `n`: 0-based day of the year, February 29 is counted.
+    Ordinal(u16),
+    /// `Mm.w.d`: day `d` (0 = Sunday) of week `w` (5 = last) of month `m`.
+    Month { month: u8, week: u8, day: u8 },
+}
+
+const DAY: i64 = 86400;
+
+struct Parser<'a>(&'a [u8]);
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.0 = &self.0[1..];
+        }
+        found
+    }
+
+    fn skip_while(&mut self, f: impl Fn(u8) -> bool) -> usize {
+        let n = self.0.iter().take_while(|&&b| f(b)).count();
+        self.0 = &self.0[n..];
+        n
+    }
+
+    fn name(&mut self) -> Option<()> {
+        let len = match self.eat(b'<') {
+            true => {
+                let len = self.skip_while(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'-');
+                self.eat(b'>').then_some(len)?
+            }
+            false => self.skip_while(|b| b.is_ascii_alphabetic()),
+        };
+        (len >= 3).then_some(())
+    }
+
+    fn number(&mut self, max: u32) -> Option<u32> {
+        let digits = self.0.iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        let mut n = 0u32;
+        for &b in &self.0[..digits] {
+            n = n.checked_mul(10)?.checked_add((b - b'0') as _)?;
+        }
+        self.0 = &self.0[digits..];
+        (n <= max).then_some(n)
+    }
+
+    /// `[+-]hh[:mm[:ss]]`, in seconds.
+    fn time(&mut self, max_hours: u32) -> Option<i32> {
+        let negative = self.eat(b'-');
+        if !negative {
+            self.eat(b'+');
+        }
+        let mut seconds = self.number(max_hours)? * 3600;
+        if self.eat(b':') {
+            seconds += self.number(59)? * 60;
+            if self.eat(b':') {
+                seconds += self.number(59)?;
+            }
+        }
+        Some(match negative {
+            true => -(seconds as i32),
+            false => seconds as i32,
+        })
+    }
+
+    fn date(&mut self) -> Option<Date> {
+        Some(match self.peek()? {
+            b'J' => {
+                self.eat(b'J');
+                Date::Julian(self.number(365).filter(|&n| n >= 1)? as _)
+            }
+            b'M' => {
+                self.eat(b'M');
+                let month = self.number(12).filter(|&n| n >= 1)? as _;
+                self.eat(b'.').then_some(())?;
+                let week = self.number(5).filter(|&n| n >= 1)? as _;
+                self.eat(b'.').then_some(())?;
+                let day = self.number(6)? as _;
+                Date::Month { month, week, day }
+            }
+            _ => Date::Ordinal(self.number(365)? as _),
+        })
+    }
+
+    fn transition(&mut self) -> Option<Transition> {
+        let date = self.date()?;
+        let time = match self.eat(b'/') {
+            true => self.time(167)?,
+            false => 2 * 3600,
+        };
+        Some(Transition { date, time })
+    }
+}
+
+impl Rule {
+    pub fn parse(s: &[u8]) -> Option<Self> {
+        let mut p = Parser(s);
+        p.name()?;
+        // POSIX offsets are west of Greenwich, hence the sign flip
+        let std_offset = -p.time(24)?;
+        let dst = match p.peek() {
+            None => None,
+            Some(_) => {
+                p.name()?;
+                let offset = match p.peek() {
+                    Some(b',') | None => std_offset + 3600,
+                    Some(_) => -p.time(24)?,
+                };
+                let (start, end) = match p.eat(b',') {
+                    true => {
+                        let start = p.transition()?;
+                        p.eat(b',').then_some(())?;
+                        (start, p.transition()?)
+                    }
+                    // the US rules, as glibc assumes when none are given
+                    false => (
+                        Transition {
+                            date: Date::Month {
+                                month: 3,
+                                week: 2,
+                                day: 0,
+                            },
+                            time: 2 * 3600,
+                        },
+                        Transition {
+                            date: Date::Month {
+                                month: 11,
+                                week: 1,
+                                day: 0,
+                            },
+                            time: 2 * 3600,
+                        },
+                    ),
+                };
+                Some(Dst { offset, start, end })
+            }
+        };
+        p.0.is_empty().then_some(Self { std_offset, dst })
+    }
+
+    pub fn lookup(&self, t: i64) -> Offset {
+        let Some(dst) = self.dst else {
+            return Offset {
+                utc_offset: self.std_offset,
+                until: i64::MAX,
+            };
+        };
+        let (year, ..) = civil_from_days(t.div_euclid(DAY));
+        // transitions of the surrounding years, as (instant, offset from then on)
+        let mut events = [(0, 0); 6];
+        for (i, year) in (year - 1..=year + 1).enumerate() {
+            // the start is given in standard time, the end in daylight time
+            events[i * 2] = (dst.start.at(year) - self.std_offset as i64, dst.offset);
+            events[i * 2 + 1] = (dst.end.at(year) - dst.offset as i64, self.std_offset);
+        }
+        events.sort_unstable_by_key(|&(instant, _)| instant);
+        let next = events.iter().position(|&(instant, _)| instant > t);
+        let current = match next {
+            Some(0) => None,
+            Some(i) => Some(i - 1),
+            None => Some(events.len() - 1),
+        };
+        Offset {
+            utc_offset: current.map_or(self.std_offset, |i| events[i].1),
+            until: next.map_or(i64::MAX, |i| events[i].0),
+        }
+    }
+}
+
+impl Transition {
+    /// Local wall-clock seconds since the epoch at which the transition happens in `year`.
+    fn at(&self, year: i64) -> i64 {
+        let jan1 = days_from_civil(year, 1, 1);
+        let day = match self.date {
+            Date::Julian(n) => {
+                let leap_shift = (is_leap(year) && n >= 60) as i64;
+                jan1 + n as i64 - 1 + leap_shift
+            }
+            Date::Ordinal(n) => jan1 + n as i64,
+            Date::Month { month, week, day } => {
+                let first = days_from_civil(year, month as _, 1);
+                // 1970-01-01 was a Thursday
+                let weekday = (first + 4).rem_euclid(7);
+                let mut d = (day as i64 - weekday).rem_euclid(7) + (week as i64 - 1) * 7;
+                if d >= month_len(year, month) {
+                    d -= 7;
+                }
+                first + d
+            }
+        };
+        day * DAY + self.time as i64
+    }
+}
+
+const fn month_len(year: i64, month: u8) -> i64 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+fn at(rule: &[u8], t: i64) -> (i32, i64) {
+    let Offset { utc_offset, until } = Rule::parse(rule).unwrap().lookup(t);
+    (utc_offset, until)
+}
+
+#[test]
+fn test_european_dst() {
+    const CET: &[u8] = b"CET-1CEST,M3.5.0,M10.5.0/3";
+    // 2026-03-29 01:00 UTC and 2026-10-25 01:00 UTC
+    assert_eq!(at(CET, 1774746000 - 1), (3600, 1774746000));
+    assert_eq!(at(CET, 1774746000), (7200, 1792890000));
+    assert_eq!(at(CET, 1792890000 - 1), (7200, 1792890000));
+    assert_eq!(at(CET, 1792890000).0, 3600);
+}
+
+#[test]
+fn test_southern_dst() {
+    const AEST: &[u8] = b"AEST-10AEDT,M10.1.0,M4.1.0/3";
+    // 2026-01-01 is summer, DST ends 2026-04-04 16:00 UTC, starts 2026-10-03 16:00 UTC
+    assert_eq!(at(AEST, 1767225600), (39600, 1775318400));
+    assert_eq!(at(AEST, 1775318400), (36000, 1791043200));
+    assert_eq!(at(AEST, 1791043200).0, 39600);
+}
+
+#[test]
+fn test_us_default_rules() {
+    // 2026-03-08 07:00 UTC and 2026-11-01 06:00 UTC
+    assert_eq!(at(b"EST5EDT", 1772953200 - 1), (-18000, 1772953200));
+    assert_eq!(at(b"EST5EDT", 1772953200), (-14400, 1793512800));
+    assert_eq!(at(b"EST5EDT,M3.2.0,M11.1.0", 1793512800).0, -18000);
+}
+
+#[test]
+fn test_julian_days() {
+    // J60 is March 1 even in leap years, 59 is February 29 in 2028
+    assert_eq!(at(b"AAA0BBB,J60/0,J300", 1835395200).1, 1835481600);
+    assert_eq!(at(b"AAA0BBB,59/0,J300", 1835395200 - 1).1, 1835395200);
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(at(b"UTC0", 0), (0, i64::MAX));
+    assert_eq!(at(b"<+0330>-3:30", 0), (12600, i64::MAX));
+    assert_eq!(at(b"<-03>3", 0), (-10800, i64::MAX));
+    assert!(Rule::parse(b"CE-1").is_none());
+    assert!(Rule::parse(b"CET-1CEST,M3.5.0").is_none());
+    assert!(Rule::parse(b"CET-1CEST,M13.5.0,M10.5.0").is_none());
+    assert!(Rule::parse(b"CET-1 trailing").is_none());
+}